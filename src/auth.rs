@@ -1,43 +1,119 @@
-use crate::utils::{console_log, fetch};
+use crate::http::{HttpClient, HttpRequest};
+use crate::jwt;
+use crate::secret::Secret;
 use serde::{Deserialize, Serialize};
-use wasm_bindgen::prelude::*;
-use web_sys::{Request, RequestInit};
 
-pub(crate) const ONE_HOUR_SECS: i64 = 60 * 60;
 const GOOGLE_OAUTH_URL: &str = "https://oauth2.googleapis.com/token";
 const GRANT_TYPE: &str = "urn:ietf:params:oauth:grant-type:jwt-bearer";
-const VISION_API_SCOPE: &str = "https://www.googleapis.com/auth/cloud-platform";
+// google refuses assertions that live longer
+const MAX_LIFETIME_SECS: u64 = 60 * 60;
+pub const CLOUD_PLATFORM_SCOPE: &str = "https://www.googleapis.com/auth/cloud-platform";
+pub const CLOUD_VISION_SCOPE: &str = "https://www.googleapis.com/auth/cloud-vision";
 
-pub(crate) async fn get_access_token(jwt: &str) -> Result<AccessTokenResponse, JsValue> {
-    let form = web_sys::FormData::new().unwrap();
-    form.append_with_str("grant_type", GRANT_TYPE).unwrap();
-    form.append_with_str("assertion", jwt).unwrap();
+/// What a service-account asks google for, so the same flow serves
+/// every google api (vision, drive, tasks, ...).
+///
+/// ```text
+/// TokenRequest::new()
+///     .scope(CLOUD_VISION_SCOPE)
+///     .subject("someone@example.org") // domain-wide delegation
+///     .lifetime(15 * 60)
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenRequest {
+    pub(crate) scopes: Vec<String>,
+    pub(crate) token_uri: Option<String>,
+    pub(crate) subject: Option<String>,
+    pub(crate) lifetime_secs: u64,
+}
+
+impl Default for TokenRequest {
+    fn default() -> Self {
+        TokenRequest {
+            scopes: Vec::new(),
+            token_uri: None,
+            subject: None,
+            lifetime_secs: MAX_LIFETIME_SECS,
+        }
+    }
+}
+
+impl TokenRequest {
+    pub fn new() -> Self {
+        TokenRequest::default()
+    }
+
+    pub fn scope(mut self, scope: &str) -> Self {
+        self.scopes.push(scope.to_string());
+        self
+    }
+
+    /// Overrides the `token_uri` of the credentials.
+    pub fn token_uri(mut self, token_uri: &str) -> Self {
+        self.token_uri = Some(token_uri.to_string());
+        self
+    }
 
-    let request = init_request(form);
-    console_log("WASM - auth.rs:", &"Got Token Response");
+    /// The user the service-account acts for, needs domain-wide delegation.
+    pub fn subject(mut self, subject: &str) -> Self {
+        self.subject = Some(subject.to_string());
+        self
+    }
+
+    /// At most one hour, longer lifetimes are cut.
+    pub fn lifetime(mut self, secs: u64) -> Self {
+        self.lifetime_secs = secs.min(MAX_LIFETIME_SECS);
+        self
+    }
+
+    /// The override, else the `token_uri` of the credentials, else google's.
+    pub(crate) fn endpoint<'a>(&'a self, creds: &'a Creds) -> &'a str {
+        match (&self.token_uri, creds.token_uri.as_str()) {
+            (Some(token_uri), _) => token_uri,
+            (None, "") => GOOGLE_OAUTH_URL,
+            (None, token_uri) => token_uri,
+        }
+    }
 
-    let res_json = fetch(request).await?;
-    match res_json.into_serde::<AccessTokenResponse>() {
-        Ok(access_token) => Ok(access_token),
-        Err(_) => Err(JsValue::NULL),
+    /// Signs the assertion and trades it at the token endpoint.
+    pub async fn send<C: HttpClient>(
+        &self,
+        creds: &Creds,
+        client: &C,
+    ) -> Result<AccessTokenResponse, String> {
+        let endpoint = self.endpoint(creds);
+        let scopes: Vec<&str> = self.scopes.iter().map(String::as_str).collect();
+        let claims = jwt::JwtClaims {
+            scopes: &scopes,
+            audience: endpoint,
+            subject: self.subject.as_deref(),
+            lifetime_secs: self.lifetime_secs,
+        };
+        let jwt = jwt::create_jwt(creds, &claims)?;
+        get_access_token(client, endpoint, &jwt).await
     }
 }
 
-fn init_request(form: web_sys::FormData) -> Request {
-    let mut opts = RequestInit::new();
-    opts.method("POST");
-    opts.body(Some(&form));
-    let request =
-        Request::new_with_str_and_init(GOOGLE_OAUTH_URL, &opts).expect("Could not create response");
-    request
+pub(crate) async fn get_access_token<C: HttpClient>(
+    client: &C,
+    token_uri: &str,
+    jwt: &str,
+) -> Result<AccessTokenResponse, String> {
+    let form = serde_urlencoded::to_string([("grant_type", GRANT_TYPE), ("assertion", jwt)])
+        .map_err(|e| e.to_string())?;
+    let request = HttpRequest::new("POST", token_uri)
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .body(form.into_bytes());
+    client.send(request).await?.into_json()
 }
 
+/// The json google offers as download for a service-account.
 #[derive(Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
-pub(crate) struct Creds {
+pub struct Creds {
     pub(crate) r#type: String,
     pub(crate) project_id: String,
     pub(crate) private_key_id: String,
-    pub(crate) private_key: String,
+    pub(crate) private_key: Secret,
     pub(crate) client_email: String,
     pub(crate) client_id: String,
     pub(crate) auth_uri: String,
@@ -46,33 +122,122 @@ pub(crate) struct Creds {
     pub(crate) client_x509_cert_url: String,
 }
 
-#[derive(Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
-pub(crate) struct Claims {
-    pub(crate) iss: String,
-    pub(crate) scope: String,
-    pub(crate) sub: String,
-    pub(crate) aud: String,
-    pub(crate) iat: i64,
-    pub(crate) exp: i64,
-}
-
-impl Claims {
-    pub(crate) fn new(email: &str, api_endpoint: &str) -> Self {
-        let now = (js_sys::Date::now() / 1000f64) as i64;
-        Claims {
-            iss: email.to_string(),
-            sub: email.to_string(),
-            scope: VISION_API_SCOPE.to_string(),
-            aud: api_endpoint.to_string(),
-            iat: now,
-            exp: (now + ONE_HOUR_SECS),
-        }
-    }
-}
-
 #[derive(Serialize, Deserialize, Debug, Default)]
 pub struct AccessTokenResponse {
-    pub access_token: String,
+    pub access_token: Secret,
     pub expires_in: u32,
     pub token_type: String,
 }
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod test {
+
+    use super::{Creds, TokenRequest, CLOUD_VISION_SCOPE};
+    use crate::http::{HttpResponse, MockServer, NativeClient};
+    use crate::jwt::verify_jwt;
+    use futures::executor::block_on;
+    use jwt_simple::prelude::*;
+
+    fn creds() -> Creds {
+        serde_json::from_str(include_str!("../test-assets/service-account.json"))
+            .expect("test fails: service-account must parse")
+    }
+
+    fn form(body: &[u8]) -> Vec<(String, String)> {
+        serde_urlencoded::from_bytes(body).expect("test fails: body must be a form")
+    }
+
+    #[test]
+    fn endpoint_comes_from_the_credentials() {
+        let mut creds = creds();
+        creds.token_uri = "https://example.org/token".to_string();
+
+        assert_eq!(
+            "https://example.org/token",
+            TokenRequest::new().endpoint(&creds)
+        );
+        assert_eq!(
+            "https://example.com/token",
+            TokenRequest::new()
+                .token_uri("https://example.com/token")
+                .endpoint(&creds)
+        );
+        creds.token_uri.clear();
+        assert_eq!(
+            "https://oauth2.googleapis.com/token",
+            TokenRequest::new().endpoint(&creds)
+        );
+    }
+
+    #[test]
+    fn lifetime_is_at_most_an_hour() {
+        assert_eq!(600, TokenRequest::new().lifetime(600).lifetime_secs);
+        assert_eq!(3600, TokenRequest::new().lifetime(86400).lifetime_secs);
+    }
+
+    #[test]
+    fn assertion_carries_scopes_subject_and_lifetime() {
+        let creds = creds();
+        let public_key = RS256KeyPair::from_pem(creds.private_key.expose())
+            .and_then(|key| key.public_key().to_pem())
+            .unwrap();
+        let server = MockServer::start(vec![HttpResponse::with_json(
+            200,
+            r#"{"access_token": "ya29.service", "expires_in": 900, "token_type": "Bearer"}"#,
+        )]);
+        let endpoint = format!("{}/token", server.url);
+
+        let token = block_on(
+            TokenRequest::new()
+                .scope(CLOUD_VISION_SCOPE)
+                .scope("https://www.googleapis.com/auth/tasks")
+                .subject("someone@example.org")
+                .lifetime(900)
+                .token_uri(&endpoint)
+                .send(&creds, &NativeClient::default()),
+        )
+        .expect("test fails: mock token endpoint answers");
+        let requests = server.requests();
+
+        assert_eq!("ya29.service", token.access_token.expose());
+        assert_eq!("/token", requests[0].url);
+        let form = form(&requests[0].body);
+        assert_eq!(
+            ("grant_type", "urn:ietf:params:oauth:grant-type:jwt-bearer"),
+            (form[0].0.as_str(), form[0].1.as_str())
+        );
+        let claims = verify_jwt(&form[1].1, &public_key, &endpoint)
+            .expect("test fails: assertion must verify");
+        assert_eq!(
+            "https://www.googleapis.com/auth/cloud-vision https://www.googleapis.com/auth/tasks",
+            claims.custom.scope
+        );
+        assert_eq!(Some("someone@example.org".to_string()), claims.subject);
+        assert_eq!(Some(creds.client_email), claims.issuer);
+        assert_eq!(
+            Some(900),
+            claims
+                .expires_at
+                .zip(claims.issued_at)
+                .map(|(exp, iat)| (exp - iat).as_secs())
+        );
+    }
+
+    #[test]
+    fn refused_assertions_are_reported() {
+        let server = MockServer::start(vec![HttpResponse::with_json(
+            400,
+            r#"{"error": "invalid_grant", "error_description": "Invalid JWT Signature."}"#,
+        )]);
+
+        let error = block_on(
+            TokenRequest::new()
+                .token_uri(&server.url)
+                .send(&creds(), &NativeClient::default()),
+        )
+        .expect_err("test fails: the endpoint refused");
+        server.requests();
+
+        assert!(error.contains("invalid_grant"));
+    }
+}
@@ -0,0 +1,254 @@
+use crate::http::{DefaultClient, HttpClient, HttpRequest, HttpResponse};
+use crate::layout::{TextLine, WordBox};
+use crate::ocr::{Document, OcrEngine};
+use crate::secret::{self, Secret};
+use crate::types::azure_read as ar;
+use async_trait::async_trait;
+use serde::Deserialize;
+
+const READ_PATH: &str = "/vision/v3.2/read/analyze";
+const KEY_HEADER: &str = "Ocp-Apim-Subscription-Key";
+
+/// What a client passes as credentials json to use azure instead of google.
+#[derive(Debug, Clone, Deserialize)]
+pub struct AzureCredentials {
+    // e.g. "https://my-resource.cognitiveservices.azure.com"
+    pub endpoint: String,
+    pub subscription_key: Secret,
+}
+
+/// The Read API of Azure Computer Vision. The image is analyzed
+/// asynchronously, the result has to be polled.
+pub struct AzureRead<C> {
+    credentials: AzureCredentials,
+    client: C,
+    pub poll_interval_ms: u32,
+    pub max_polls: u32,
+}
+
+impl AzureRead<DefaultClient> {
+    pub fn new(credentials_json: &str) -> Result<Self, String> {
+        let credentials = secret::from_credentials_json(credentials_json)?;
+        Ok(AzureRead::with_client(
+            credentials,
+            DefaultClient::default(),
+        ))
+    }
+}
+
+impl<C: HttpClient> AzureRead<C> {
+    pub fn with_client(credentials: AzureCredentials, client: C) -> Self {
+        AzureRead {
+            credentials,
+            client,
+            poll_interval_ms: 1000,
+            max_polls: 30,
+        }
+    }
+
+    // answers with the url of the operation that has to be polled
+    async fn analyze(&self, image: Vec<u8>) -> Result<String, String> {
+        let url = format!(
+            "{}{}",
+            self.credentials.endpoint.trim_end_matches('/'),
+            READ_PATH
+        );
+        let request = HttpRequest::new("POST", &url)
+            .secret_header(KEY_HEADER, self.credentials.subscription_key.clone())
+            .header("Content-Type", "application/octet-stream")
+            .body(image);
+        let response = self.client.send(request).await?;
+        if !response.is_success() {
+            return Err(api_error(&response));
+        }
+        response
+            .header("Operation-Location")
+            .map(str::to_string)
+            .ok_or_else(|| "Operation-Location is missing".to_string())
+    }
+
+    async fn poll(&self, operation_url: &str) -> Result<ar::AnalyzeResult, String> {
+        for _ in 0..self.max_polls {
+            self.client.wait(self.poll_interval_ms).await;
+            let request = HttpRequest::new("GET", operation_url)
+                .secret_header(KEY_HEADER, self.credentials.subscription_key.clone());
+            let response = self.client.send(request).await?;
+            if !response.is_success() {
+                return Err(api_error(&response));
+            }
+            let operation: ar::ReadOperation = response.json()?;
+            match (operation.status.as_str(), operation.analyze_result) {
+                ("succeeded", Some(result)) => return Ok(result),
+                ("succeeded", None) => return Err("succeeded without a result".to_string()),
+                ("failed", _) => return Err("azure could not read the image".to_string()),
+                // notStarted or running
+                _ => continue,
+            }
+        }
+        Err(format!("no result after {} polls", self.max_polls))
+    }
+}
+
+#[async_trait(?Send)]
+impl<C: HttpClient> OcrEngine for AzureRead<C> {
+    async fn recognize(&self, img_data: &str) -> Result<Document, String> {
+        let image = base64::decode(img_data).map_err(|e| e.to_string())?;
+        let operation_url = self.analyze(image).await?;
+        let result = self.poll(&operation_url).await?;
+        Ok(result_to_document(result))
+    }
+}
+
+fn api_error(response: &HttpResponse) -> String {
+    match response.json::<ar::ErrorResponse>() {
+        Ok(e) => format!("{}: {}", e.error.code, e.error.message),
+        Err(_) => format!("azure answered with status {}", response.status),
+    }
+}
+
+fn result_to_document(result: ar::AnalyzeResult) -> Document {
+    let lines: Vec<TextLine> = result
+        .read_results
+        .into_iter()
+        .flat_map(|page| page.lines)
+        .map(|line| {
+            let words = line
+                .words
+                .iter()
+                .map(|w| word_box(&w.text, &w.bounding_box, w.confidence))
+                .collect();
+            TextLine::from_words(&line.text, words)
+        })
+        .collect();
+    let text = lines
+        .iter()
+        .map(|l| l.text.as_str())
+        .collect::<Vec<_>>()
+        .join("\n");
+    Document { text, lines }
+}
+
+// the polygon can be rotated, the box spans all of its corners
+fn word_box(text: &str, polygon: &[f64], confidence: f32) -> WordBox {
+    let xs = polygon.iter().step_by(2);
+    let ys = polygon.iter().skip(1).step_by(2);
+    let (left, right) = span(xs);
+    let (top, bottom) = span(ys);
+    WordBox {
+        text: text.to_string(),
+        left,
+        top,
+        width: right.saturating_sub(left),
+        height: bottom.saturating_sub(top),
+        confidence: Some(confidence),
+    }
+}
+
+fn span<'a>(values: impl Iterator<Item = &'a f64>) -> (u32, u32) {
+    let (min, max) = values.fold((f64::MAX, f64::MIN), |(min, max), v| {
+        (min.min(*v), max.max(*v))
+    });
+    if min > max {
+        return (0, 0);
+    }
+    (min.max(0.0).round() as u32, max.max(0.0).round() as u32)
+}
+
+#[cfg(test)]
+mod test {
+
+    extern crate wasm_bindgen_test;
+
+    use wasm_bindgen_test::*;
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    use super::{AzureCredentials, AzureRead};
+    use crate::http::{HttpResponse, Replay};
+    use crate::ocr::OcrEngine;
+    use futures::executor::block_on;
+
+    const OPERATION_URL: &str =
+        "https://westeurope.api.cognitive.microsoft.com/vision/v3.2/read/analyzeResults/1f2d";
+
+    fn engine(responses: Vec<HttpResponse>) -> AzureRead<Replay> {
+        let credentials = AzureCredentials {
+            endpoint: "https://westeurope.api.cognitive.microsoft.com/".to_string(),
+            subscription_key: "secret-key".into(),
+        };
+        AzureRead::with_client(credentials, Replay::new(responses))
+    }
+
+    fn accepted() -> HttpResponse {
+        HttpResponse {
+            status: 202,
+            headers: vec![("operation-location".to_string(), OPERATION_URL.to_string())],
+            body: Vec::new(),
+        }
+    }
+
+    fn ok(body: &[u8]) -> HttpResponse {
+        HttpResponse {
+            status: 200,
+            body: body.to_vec(),
+            ..HttpResponse::default()
+        }
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn analyzes_then_polls_until_succeeded() {
+        let engine = engine(vec![
+            accepted(),
+            ok(include_bytes!(
+                "../test-assets/azure-read-running-response.json"
+            )),
+            ok(include_bytes!("../test-assets/azure-read-response.json")),
+        ]);
+
+        let document = block_on(engine.recognize(&base64::encode(b"png")))
+            .expect("test fails: recorded responses must give a document");
+
+        assert_eq!("Einkaufen:\n2 Milch\nBrot", document.text);
+        let milch = &document.lines[1];
+        assert_eq!((48, 66, 32), (milch.left, milch.top, milch.height));
+        assert_eq!(2, milch.words.len());
+        assert_eq!(Some(0.72), document.lines[2].confidence());
+
+        let requests = engine.client.requests.borrow();
+        assert_eq!(3, requests.len());
+        assert_eq!(
+            "https://westeurope.api.cognitive.microsoft.com/vision/v3.2/read/analyze",
+            requests[0].url
+        );
+        assert_eq!(b"png".to_vec(), requests[0].body);
+        assert!(requests[0].headers.contains(&(
+            "Ocp-Apim-Subscription-Key".to_string(),
+            "secret-key".to_string()
+        )));
+        assert_eq!("GET", requests[2].method);
+        assert_eq!(OPERATION_URL, requests[2].url);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn api_errors_are_reported() {
+        let engine = engine(vec![HttpResponse {
+            status: 401,
+            body: br#"{"error": {"code": "401", "message": "Access denied due to invalid subscription key."}}"#.to_vec(),
+            ..HttpResponse::default()
+        }]);
+
+        let error = block_on(engine.recognize(&base64::encode(b"png")))
+            .expect_err("test fails: a 401 must be an error");
+        assert_eq!("401: Access denied due to invalid subscription key.", error);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn gives_up_after_max_polls() {
+        let running = include_bytes!("../test-assets/azure-read-running-response.json");
+        let mut engine = engine(vec![accepted(), ok(running), ok(running)]);
+        engine.max_polls = 2;
+
+        let error = block_on(engine.recognize(&base64::encode(b"png")))
+            .expect_err("test fails: polling must stop");
+        assert_eq!("no result after 2 polls", error);
+    }
+}
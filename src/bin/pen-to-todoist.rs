@@ -0,0 +1,107 @@
+//! Command line access to the parts of the pipeline that need no browser.
+//!
+//! ```text
+//! pen-to-todoist export [--format markdown] [--options options.json]
+//!                       [--credentials credentials.json] [--output list.md] <image>
+//! ```
+//!
+//! Without `--credentials` the image is read by a local tesseract
+//! (only with the `tesseract` feature).
+
+use futures::executor::block_on;
+use pen_to_todoist::export::{self, ExportFormat};
+use pen_to_todoist::ocr::OcrEngine;
+use pen_to_todoist::options::Options;
+use pen_to_todoist::pipeline::Context;
+use pen_to_todoist::{ocr_engine, recognize_items};
+use std::fs;
+
+const USAGE: &str = "usage: pen-to-todoist export [--format markdown|org|todo_txt|csv|json|ics] \
+[--options <file>] [--credentials <file>] [--output <file>] <image>";
+
+#[derive(Default)]
+struct ExportArgs {
+    format: ExportFormat,
+    options: Option<String>,
+    credentials: Option<String>,
+    output: Option<String>,
+    image: Option<String>,
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let result = match args.first().map(String::as_str) {
+        Some("export") => parse_export_args(&args[1..]).and_then(|args| export(&args)),
+        _ => Err(USAGE.to_string()),
+    };
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
+
+fn parse_export_args(args: &[String]) -> Result<ExportArgs, String> {
+    let mut parsed = ExportArgs::default();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().cloned().ok_or(format!("{} needs a value", arg));
+        match arg.as_str() {
+            "--format" | "-f" => parsed.format = ExportFormat::from_name(&value()?)?,
+            "--options" => parsed.options = Some(value()?),
+            "--credentials" => parsed.credentials = Some(value()?),
+            "--output" | "-o" => parsed.output = Some(value()?),
+            other if other.starts_with('-') => {
+                return Err(format!("unknown flag {}\n{}", other, USAGE))
+            }
+            image => parsed.image = Some(image.to_string()),
+        }
+    }
+    Ok(parsed)
+}
+
+fn export(args: &ExportArgs) -> Result<(), String> {
+    let image_path = args.image.as_ref().ok_or(USAGE)?;
+    let image = fs::read(image_path).map_err(|e| format!("{}: {}", image_path, e))?;
+    let options = match &args.options {
+        Some(path) => Options::from_json(&read(path)?)?,
+        None => Options::default(),
+    };
+    let engine = engine(&options, args.credentials.as_deref())?;
+
+    let items = block_on(recognize_items(
+        engine.as_ref(),
+        &base64::encode(image),
+        &options,
+        false,
+        &Context::default(),
+    ))?;
+    let text = export::render(&items, args.format, &options.export);
+    match &args.output {
+        Some(path) => fs::write(path, text).map_err(|e| format!("{}: {}", path, e)),
+        None => {
+            print!("{}", text);
+            Ok(())
+        }
+    }
+}
+
+fn engine(options: &Options, credentials: Option<&str>) -> Result<Box<dyn OcrEngine>, String> {
+    match credentials {
+        Some(path) => ocr_engine(options, &read(path)?),
+        None => local_engine(),
+    }
+}
+
+#[cfg(all(feature = "tesseract", not(target_arch = "wasm32")))]
+fn local_engine() -> Result<Box<dyn OcrEngine>, String> {
+    Ok(Box::new(pen_to_todoist::tesseract::Tesseract::default()))
+}
+
+#[cfg(not(all(feature = "tesseract", not(target_arch = "wasm32"))))]
+fn local_engine() -> Result<Box<dyn OcrEngine>, String> {
+    Err("--credentials is needed, this build has no local ocr (feature tesseract)".to_string())
+}
+
+fn read(path: &str) -> Result<String, String> {
+    fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))
+}
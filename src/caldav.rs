@@ -0,0 +1,430 @@
+use crate::http::{DefaultClient, HttpClient, HttpRequest, HttpResponse};
+use crate::ical::{self, Vtodo};
+use crate::secret::{self, Secret};
+use crate::sink::{SinkProject, SinkTask, TaskDraft, TaskSink};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+
+const PROPFIND_CALENDARS: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<d:propfind xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+  <d:prop>
+    <d:displayname/>
+    <d:resourcetype/>
+    <c:supported-calendar-component-set/>
+  </d:prop>
+</d:propfind>"#;
+
+const QUERY_TODOS: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<c:calendar-query xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+  <d:prop>
+    <c:calendar-data/>
+  </d:prop>
+  <c:filter>
+    <c:comp-filter name="VCALENDAR">
+      <c:comp-filter name="VTODO"/>
+    </c:comp-filter>
+  </c:filter>
+</c:calendar-query>"#;
+
+/// Where the calendars of the user live (e.g. the calendar home of
+/// nextcloud, `https://cloud.example.org/remote.php/dav/calendars/anna/`).
+#[derive(Debug, Clone, Deserialize)]
+pub struct CalDavCredentials {
+    pub url: String,
+    pub username: String,
+    pub password: Secret,
+}
+
+/// A caldav server, the projects are the calendars that can hold todos
+/// and the tasks are VTODOs.
+/// Ids are the urls of the calendars and of the `.ics` resources.
+pub struct CalDav<C> {
+    credentials: CalDavCredentials,
+    client: C,
+    // the DTSTAMP of the created todos
+    pub clock: fn() -> DateTime<Utc>,
+}
+
+impl CalDav<DefaultClient> {
+    pub fn new(credentials_json: &str) -> Result<Self, String> {
+        let credentials = secret::from_credentials_json(credentials_json)?;
+        Ok(CalDav::with_client(credentials, DefaultClient::default()))
+    }
+}
+
+impl<C: HttpClient> CalDav<C> {
+    pub fn with_client(mut credentials: CalDavCredentials, client: C) -> Self {
+        if !credentials.url.ends_with('/') {
+            credentials.url.push('/');
+        }
+        CalDav {
+            credentials,
+            client,
+            clock: Utc::now,
+        }
+    }
+
+    fn request(&self, method: &str, url: &str) -> HttpRequest {
+        let login = Secret::new(format!(
+            "{}:{}",
+            self.credentials.username,
+            self.credentials.password.expose()
+        ));
+        let login = Secret::new(base64::encode(login.expose()));
+        HttpRequest::new(method, url).secret_header(
+            "Authorization",
+            Secret::new(format!("Basic {}", login.expose())),
+        )
+    }
+
+    async fn send(&self, request: HttpRequest) -> Result<HttpResponse, String> {
+        let response = self.client.send(request).await?;
+        if response.is_success() {
+            Ok(response)
+        } else {
+            Err(format!(
+                "status {}: {}",
+                response.status,
+                String::from_utf8_lossy(&response.body)
+            ))
+        }
+    }
+
+    async fn xml_request(
+        &self,
+        method: &str,
+        url: &str,
+        depth: &str,
+        body: &str,
+    ) -> Result<String, String> {
+        let request = self
+            .request(method, url)
+            .header("Depth", depth)
+            .header("Content-Type", "application/xml; charset=utf-8")
+            .body(body.as_bytes().to_vec());
+        let response = self.send(request).await?;
+        Ok(String::from_utf8_lossy(&response.body).into_owned())
+    }
+
+    // hrefs are mostly absolute paths on the same server
+    fn resolve(&self, href: &str) -> String {
+        if href.starts_with("http://") || href.starts_with("https://") {
+            return href.to_string();
+        }
+        let url = &self.credentials.url;
+        let after_scheme = url.find("://").map(|i| i + 3).unwrap_or(0);
+        let origin_end = url[after_scheme..]
+            .find('/')
+            .map(|i| after_scheme + i)
+            .unwrap_or(url.len());
+        format!("{}{}", &url[..origin_end], href)
+    }
+}
+
+#[async_trait(?Send)]
+impl<C: HttpClient> TaskSink for CalDav<C> {
+    async fn list_projects(&self) -> Result<Vec<SinkProject>, String> {
+        let xml = self
+            .xml_request("PROPFIND", &self.credentials.url, "1", PROPFIND_CALENDARS)
+            .await?;
+        Ok(elements(&xml, "response")
+            .into_iter()
+            .filter(|response| {
+                let is_calendar = elements(response, "resourcetype")
+                    .iter()
+                    .any(|types| !elements(types, "calendar").is_empty());
+                // without a component set, a calendar takes every component
+                let components = elements(response, "supported-calendar-component-set");
+                is_calendar
+                    && components
+                        .first()
+                        .is_none_or(|set| set.contains("\"VTODO\""))
+            })
+            .filter_map(|response| {
+                let href = elements(response, "href").into_iter().next()?;
+                let name = elements(response, "displayname")
+                    .into_iter()
+                    .next()
+                    .unwrap_or_default();
+                Some(SinkProject {
+                    id: self.resolve(href.trim()),
+                    name: unescape_xml(name.trim()),
+                })
+            })
+            .collect())
+    }
+
+    async fn ensure_project(&self, name: &str) -> Result<String, String> {
+        let calendars = self.list_projects().await?;
+        if let Some(calendar) = calendars.iter().find(|c| c.name.eq_ignore_ascii_case(name)) {
+            return Ok(calendar.id.clone());
+        }
+        let url = format!("{}{}/", self.credentials.url, uuid::Uuid::new_v4());
+        let body = format!(
+            r#"<?xml version="1.0" encoding="utf-8"?>
+<c:mkcalendar xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+  <d:set>
+    <d:prop>
+      <d:displayname>{}</d:displayname>
+      <c:supported-calendar-component-set>
+        <c:comp name="VTODO"/>
+      </c:supported-calendar-component-set>
+    </d:prop>
+  </d:set>
+</c:mkcalendar>"#,
+            escape_xml(name)
+        );
+        let request = self
+            .request("MKCALENDAR", &url)
+            .header("Content-Type", "application/xml; charset=utf-8")
+            .body(body.into_bytes());
+        self.send(request).await?;
+        Ok(url)
+    }
+
+    async fn create_task(&self, project_id: &str, draft: &TaskDraft) -> Result<String, String> {
+        let uid = uuid::Uuid::new_v4().to_string();
+        let mut todo = Vtodo::from_item(draft.item, &uid, (self.clock)());
+        todo.completed = draft.completed;
+        // the parent is known by its url, "<calendar>/<uid>.ics"
+        todo.parent_uid = draft.parent_id.as_ref().and_then(|parent| {
+            let file = parent.rsplit('/').next()?;
+            Some(file.strip_suffix(".ics").unwrap_or(file).to_string())
+        });
+
+        let collection = project_id.trim_end_matches('/');
+        let url = format!("{}/{}.ics", collection, uid);
+        let request = self
+            .request("PUT", &url)
+            .header("Content-Type", "text/calendar; charset=utf-8")
+            // never overwrite an existing todo
+            .header("If-None-Match", "*")
+            .body(ical::calendar(&[todo]).into_bytes());
+        self.send(request).await?;
+        Ok(url)
+    }
+
+    async fn find_tasks(&self, project_id: &str) -> Result<Vec<SinkTask>, String> {
+        let xml = self
+            .xml_request("REPORT", project_id, "1", QUERY_TODOS)
+            .await?;
+        Ok(elements(&xml, "response")
+            .into_iter()
+            .filter_map(|response| {
+                let href = elements(response, "href").into_iter().next()?;
+                let data = elements(response, "calendar-data").into_iter().next()?;
+                let summary = ical::unfold(&unescape_xml(data))
+                    .iter()
+                    .find_map(|line| property(line, "SUMMARY"))?;
+                Some(SinkTask {
+                    id: self.resolve(href.trim()),
+                    content: summary,
+                })
+            })
+            .collect())
+    }
+}
+
+// the value of a content line, if it is the property
+// (e.g. "SUMMARY;LANGUAGE=de:Milch")
+fn property(line: &str, name: &str) -> Option<String> {
+    let (key, value) = line.split_once(':')?;
+    let key_name = key.split(';').next()?;
+    key_name
+        .eq_ignore_ascii_case(name)
+        .then(|| ical::unescape_text(value))
+}
+
+/// The contents of every element with that local name, whatever
+/// prefix the server uses for its namespaces. Enough for the
+/// multistatus answers, not a general xml parser.
+fn elements<'a>(xml: &'a str, local_name: &str) -> Vec<&'a str> {
+    let mut found = Vec::new();
+    let mut rest = xml;
+    while let Some(start) = rest.find('<') {
+        rest = &rest[start + 1..];
+        let tag_end = match rest.find('>') {
+            Some(end) => end,
+            None => break,
+        };
+        let tag = &rest[..tag_end];
+        let name = tag.split_whitespace().next().unwrap_or_default();
+        let self_closing = tag.ends_with('/');
+        let name = name.trim_end_matches('/');
+        if name.starts_with('/') || local(name) != local_name {
+            continue;
+        }
+        rest = &rest[tag_end + 1..];
+        if self_closing {
+            found.push("");
+            continue;
+        }
+        let close = format!("</{}>", name);
+        match rest.find(&close) {
+            Some(end) => {
+                found.push(&rest[..end]);
+                rest = &rest[end + close.len()..];
+            }
+            None => break,
+        }
+    }
+    found
+}
+
+fn local(name: &str) -> &str {
+    name.rsplit(':').next().unwrap_or(name)
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+fn unescape_xml(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&#13;", "\r")
+        .replace("&amp;", "&")
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod test {
+
+    use super::{CalDav, CalDavCredentials};
+    use crate::http::{HttpResponse, MockServer, NativeClient};
+    use crate::parser::ListItem;
+    use crate::sink::{TaskDraft, TaskSink};
+    use chrono::{DateTime, TimeZone, Utc};
+    use futures::executor::block_on;
+
+    fn fixed_clock() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2023, 3, 11, 14, 2, 10).unwrap()
+    }
+
+    fn sink(server: &MockServer) -> CalDav<NativeClient> {
+        let credentials = CalDavCredentials {
+            url: format!("{}/dav/calendars/anna", server.url),
+            username: "anna".to_string(),
+            password: "geheim".into(),
+        };
+        let mut sink = CalDav::with_client(credentials, NativeClient::default());
+        sink.clock = fixed_clock;
+        sink
+    }
+
+    fn multistatus(status: u16, xml: &str) -> HttpResponse {
+        HttpResponse {
+            status,
+            headers: vec![("Content-Type".to_string(), "application/xml".to_string())],
+            body: xml.as_bytes().to_vec(),
+        }
+    }
+
+    #[test]
+    fn only_calendars_for_todos_are_projects() {
+        let server = MockServer::start(vec![multistatus(
+            207,
+            r#"<?xml version="1.0"?>
+<d:multistatus xmlns:d="DAV:" xmlns:cal="urn:ietf:params:xml:ns:caldav">
+  <d:response>
+    <d:href>/dav/calendars/anna/</d:href>
+    <d:propstat><d:prop><d:resourcetype><d:collection/></d:resourcetype></d:prop></d:propstat>
+  </d:response>
+  <d:response>
+    <d:href>/dav/calendars/anna/termine/</d:href>
+    <d:propstat><d:prop>
+      <d:displayname>Termine</d:displayname>
+      <d:resourcetype><d:collection/><cal:calendar/></d:resourcetype>
+      <cal:supported-calendar-component-set><cal:comp name="VEVENT"/></cal:supported-calendar-component-set>
+    </d:prop></d:propstat>
+  </d:response>
+  <d:response>
+    <d:href>/dav/calendars/anna/einkauf/</d:href>
+    <d:propstat><d:prop>
+      <d:displayname>Einkauf &amp; Haushalt</d:displayname>
+      <d:resourcetype><d:collection/><cal:calendar/></d:resourcetype>
+      <cal:supported-calendar-component-set><cal:comp name="VTODO"/></cal:supported-calendar-component-set>
+    </d:prop></d:propstat>
+  </d:response>
+</d:multistatus>"#,
+        )]);
+        let sink = sink(&server);
+        let url = server.url.clone();
+
+        let projects = block_on(sink.list_projects()).expect("test fails: mock server answers");
+        let requests = server.requests();
+
+        assert_eq!(1, projects.len());
+        assert_eq!("Einkauf & Haushalt", projects[0].name);
+        assert_eq!(
+            format!("{}/dav/calendars/anna/einkauf/", url),
+            projects[0].id
+        );
+        assert_eq!("PROPFIND", requests[0].method);
+        assert!(requests[0].headers.contains(&(
+            "authorization".to_string(),
+            "Basic YW5uYTpnZWhlaW0=".to_string()
+        )));
+    }
+
+    #[test]
+    fn todos_are_put_as_ics() {
+        let server = MockServer::start(vec![HttpResponse {
+            status: 201,
+            ..HttpResponse::default()
+        }]);
+        let sink = sink(&server);
+        let calendar = format!("{}/dav/calendars/anna/einkauf/", server.url);
+        let item = ListItem {
+            content: "Milch, 3,5%".to_string(),
+            ..ListItem::default()
+        };
+        let draft = TaskDraft {
+            item: &item,
+            parent_id: Some(format!("{}party.ics", calendar)),
+            completed: true,
+        };
+
+        let id =
+            block_on(sink.create_task(&calendar, &draft)).expect("test fails: mock server answers");
+        let requests = server.requests();
+
+        assert!(id.starts_with(&calendar) && id.ends_with(".ics"));
+        assert_eq!("PUT", requests[0].method);
+        assert!(requests[0]
+            .headers
+            .contains(&("if-none-match".to_string(), "*".to_string())));
+        let body = String::from_utf8(requests[0].body.clone()).unwrap();
+        assert!(body.contains("SUMMARY:Milch\\, 3\\,5%\r\n"));
+        assert!(body.contains("DTSTAMP:20230311T140210Z\r\n"));
+        assert!(body.contains("STATUS:COMPLETED\r\n"));
+        assert!(body.contains("RELATED-TO;RELTYPE=PARENT:party\r\n"));
+    }
+
+    #[test]
+    fn existing_todos_are_found() {
+        let server = MockServer::start(vec![multistatus(
+            207,
+            "<multistatus xmlns=\"DAV:\"><response>\
+             <href>/dav/calendars/anna/einkauf/a.ics</href>\
+             <propstat><prop><C:calendar-data xmlns:C=\"urn:ietf:params:xml:ns:caldav\">\
+             BEGIN:VCALENDAR\r\nBEGIN:VTODO\r\nUID:a\r\nSUMMARY;LANGUAGE=de:Brot und\r\n  Butter\r\n\
+             END:VTODO\r\nEND:VCALENDAR\r\n\
+             </C:calendar-data></prop></propstat></response></multistatus>",
+        )]);
+        let sink = sink(&server);
+        let calendar = format!("{}/dav/calendars/anna/einkauf/", server.url);
+
+        let tasks = block_on(sink.find_tasks(&calendar)).expect("test fails: mock server answers");
+        let requests = server.requests();
+
+        assert_eq!("REPORT", requests[0].method);
+        assert_eq!(1, tasks.len());
+        assert_eq!("Brot und Butter", tasks[0].content);
+        assert_eq!(format!("{}a.ics", calendar), tasks[0].id);
+    }
+}
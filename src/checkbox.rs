@@ -0,0 +1,183 @@
+use crate::layout::{TextLine, WordBox};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckboxState {
+    Checked,
+    Unchecked,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct CheckboxConfig {
+    pub checked: CheckedHandling,
+}
+
+/// What happens with items that are already ticked on paper.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckedHandling {
+    #[default]
+    Skip,
+    // create the task and close it right away
+    Complete,
+    // create the task like any other
+    Keep,
+}
+
+const CHECKED: &[&str] = &[
+    "☑", "☒", "✓", "✔", "✗", "✘", "⊠", "🗹", "[x]", "[X]", "[✓]", "(x)", "(X)",
+];
+const UNCHECKED: &[&str] = &["☐", "□", "▢", "◻", "❏", "❐", "[ ]", "[]", "()"];
+
+// these are only a checkbox if the word is a small square,
+// otherwise they are text ("O Tannenbaum", "x Milch", "口罩").
+// A box is often read as one of the CJK characters.
+const CHECKED_LETTERS: &[&str] = &["x", "X", "v", "V", "区", "図", "凶"];
+const UNCHECKED_LETTERS: &[&str] = &["O", "o", "0", "D", "口"];
+
+/// Looks for a checkbox at the start of the line. Returns its state and
+/// the text without the checkbox.
+pub fn detect_checkbox(line: &TextLine) -> (Option<CheckboxState>, String) {
+    let text = line.text.trim_start();
+
+    // "[ ]" contains whitespace, so it cannot be taken from the first word
+    for (glyphs, state) in [
+        (CHECKED, CheckboxState::Checked),
+        (UNCHECKED, CheckboxState::Unchecked),
+    ] {
+        if let Some(rest) = glyphs.iter().find_map(|g| text.strip_prefix(g)) {
+            return (Some(state), rest.trim_start().to_string());
+        }
+    }
+
+    let Some((first, rest)) = text.split_once(char::is_whitespace) else {
+        return (None, line.text.clone());
+    };
+    let square = line
+        .words
+        .first()
+        .is_some_and(|w| w.text == first && is_small_square(w, line));
+    if square {
+        if CHECKED_LETTERS.contains(&first) {
+            return (Some(CheckboxState::Checked), rest.trim_start().to_string());
+        }
+        if UNCHECKED_LETTERS.contains(&first) {
+            return (
+                Some(CheckboxState::Unchecked),
+                rest.trim_start().to_string(),
+            );
+        }
+    }
+    (None, line.text.clone())
+}
+
+// roughly as wide as high and not taller than the rest of the line
+fn is_small_square(word: &WordBox, line: &TextLine) -> bool {
+    if word.height == 0 || word.width == 0 {
+        return false;
+    }
+    let ratio = word.width as f32 / word.height as f32;
+    (0.7..=1.4).contains(&ratio) && word.height <= line.height
+}
+
+#[cfg(test)]
+mod test {
+
+    extern crate wasm_bindgen_test;
+
+    use wasm_bindgen_test::*;
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    use super::{detect_checkbox, CheckboxState};
+    use crate::layout::{TextLine, WordBox};
+
+    fn word(text: &str, left: u32, width: u32, height: u32) -> WordBox {
+        WordBox {
+            text: text.to_string(),
+            left,
+            top: 0,
+            width,
+            height,
+            confidence: None,
+        }
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn checkbox_glyphs_are_classified() {
+        assert_eq!(
+            (Some(CheckboxState::Checked), "Milch".to_string()),
+            detect_checkbox(&TextLine::new("☑ Milch"))
+        );
+        assert_eq!(
+            (Some(CheckboxState::Checked), "Milch".to_string()),
+            detect_checkbox(&TextLine::new("[x] Milch"))
+        );
+        assert_eq!(
+            (Some(CheckboxState::Unchecked), "Brot".to_string()),
+            detect_checkbox(&TextLine::new("☐Brot"))
+        );
+        assert_eq!(
+            (Some(CheckboxState::Unchecked), "Brot".to_string()),
+            detect_checkbox(&TextLine::new("[ ] Brot"))
+        );
+        assert_eq!(
+            (None, "Brot".to_string()),
+            detect_checkbox(&TextLine::new("Brot"))
+        );
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn letters_need_a_small_square_box() {
+        let ticked = TextLine::from_words(
+            "X Eier",
+            vec![word("X", 0, 28, 30), word("Eier", 40, 60, 30)],
+        );
+        assert_eq!(
+            (Some(CheckboxState::Checked), "Eier".to_string()),
+            detect_checkbox(&ticked)
+        );
+
+        let open = TextLine::from_words(
+            "O Eier",
+            vec![word("O", 0, 30, 30), word("Eier", 40, 60, 30)],
+        );
+        assert_eq!(
+            (Some(CheckboxState::Unchecked), "Eier".to_string()),
+            detect_checkbox(&open)
+        );
+
+        let text = TextLine::from_words(
+            "O Tannenbaum",
+            vec![word("O", 0, 12, 30), word("Tannenbaum", 20, 160, 30)],
+        );
+        assert_eq!((None, "O Tannenbaum".to_string()), detect_checkbox(&text));
+
+        assert_eq!(
+            (None, "x Milch".to_string()),
+            detect_checkbox(&TextLine::new("x Milch"))
+        );
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn cjk_boxes_need_a_small_square_box_too() {
+        let open = TextLine::from_words(
+            "口 Tofu",
+            vec![word("口", 0, 30, 30), word("Tofu", 40, 60, 30)],
+        );
+        assert_eq!(
+            (Some(CheckboxState::Unchecked), "Tofu".to_string()),
+            detect_checkbox(&open)
+        );
+
+        assert_eq!(
+            (None, "口罩".to_string()),
+            detect_checkbox(&TextLine::new("口罩"))
+        );
+        assert_eq!(
+            (None, "区役所 anrufen".to_string()),
+            detect_checkbox(&TextLine::new("区役所 anrufen"))
+        );
+    }
+}
@@ -0,0 +1,328 @@
+use crate::layout::TextLine;
+use crate::ocr::{Document, OcrEngine};
+use async_trait::async_trait;
+use futures::future::join_all;
+use serde::Serialize;
+
+// lines of two engines are the same line if they share this much
+// of the height of the smaller one
+const MIN_VERTICAL_OVERLAP: f32 = 0.5;
+
+/// Runs several engines on the same image and votes per line.
+#[derive(Default)]
+pub struct Ensemble {
+    // earlier engines win ties
+    engines: Vec<(String, Box<dyn OcrEngine>)>,
+}
+
+/// Why and by whom a line of the result was chosen.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LineReport {
+    pub text: String,
+    pub engine: String,
+    // "majority" if more engines read the same text, otherwise "confidence"
+    pub decided_by: String,
+    pub candidates: Vec<Candidate>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Candidate {
+    pub engine: String,
+    pub text: String,
+    pub confidence: Option<f32>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct EnsembleReport {
+    pub lines: Vec<LineReport>,
+    // engines that failed, the others still vote
+    pub errors: Vec<(String, String)>,
+}
+
+impl Ensemble {
+    pub fn new() -> Self {
+        Ensemble::default()
+    }
+
+    pub fn with_engine(mut self, name: &str, engine: Box<dyn OcrEngine>) -> Self {
+        self.engines.push((name.to_string(), engine));
+        self
+    }
+
+    /// All engines run concurrently (on wasm their requests are
+    /// pending at the same time), then their lines are aligned and voted on.
+    pub async fn recognize_with_report(
+        &self,
+        img_data: &str,
+    ) -> Result<(Document, EnsembleReport), String> {
+        let results = join_all(self.engines.iter().map(|(_, e)| e.recognize(img_data))).await;
+
+        let mut documents = Vec::new();
+        let mut report = EnsembleReport::default();
+        for ((name, _), result) in self.engines.iter().zip(results) {
+            match result {
+                Ok(document) => documents.push((name.as_str(), document)),
+                Err(e) => report.errors.push((name.clone(), e)),
+            }
+        }
+        if documents.is_empty() {
+            let errors: Vec<String> = report
+                .errors
+                .iter()
+                .map(|(name, e)| format!("{}: {}", name, e))
+                .collect();
+            return Err(format!("every engine failed ({})", errors.join(", ")));
+        }
+
+        let (lines, line_reports) = vote(align(&documents));
+        report.lines = line_reports;
+        let text = lines
+            .iter()
+            .map(|l| l.text.as_str())
+            .collect::<Vec<_>>()
+            .join("\n");
+        Ok((Document { text, lines }, report))
+    }
+}
+
+#[async_trait(?Send)]
+impl OcrEngine for Ensemble {
+    async fn recognize(&self, img_data: &str) -> Result<Document, String> {
+        Ok(self.recognize_with_report(img_data).await?.0)
+    }
+}
+
+// the lines of all engines that were found at the same spot,
+// at most one line per engine
+type Group<'a> = Vec<(&'a str, &'a TextLine)>;
+
+fn align<'a>(documents: &'a [(&'a str, Document)]) -> Vec<Group<'a>> {
+    let mut groups: Vec<Group<'a>> = Vec::new();
+    for (engine, document) in documents {
+        for line in &document.lines {
+            let best = groups
+                .iter_mut()
+                .filter(|group| !group.iter().any(|(e, _)| e == engine))
+                .map(|group| (overlap(group[0].1, line), group))
+                .filter(|(overlap, _)| *overlap >= MIN_VERTICAL_OVERLAP)
+                .max_by(|(a, _), (b, _)| a.total_cmp(b));
+            match best {
+                Some((_, group)) => group.push((engine, line)),
+                None => groups.push(vec![(engine, line)]),
+            }
+        }
+    }
+    groups.sort_by_key(|group| group[0].1.top);
+    groups
+}
+
+// shared height relative to the smaller line, 0.0 if they
+// do not touch horizontally
+fn overlap(a: &TextLine, b: &TextLine) -> f32 {
+    let (a_left, a_right) = horizontal(a);
+    let (b_left, b_right) = horizontal(b);
+    if a_right <= b_left || b_right <= a_left {
+        return 0.0;
+    }
+    let top = a.top.max(b.top);
+    let bottom = (a.top + a.height).min(b.top + b.height);
+    let smaller = a.height.min(b.height);
+    if smaller == 0 || bottom <= top {
+        return 0.0;
+    }
+    (bottom - top) as f32 / smaller as f32
+}
+
+fn horizontal(line: &TextLine) -> (u32, u32) {
+    let right = line.words.iter().map(|w| w.left + w.width).max();
+    (line.left, right.unwrap_or(line.left + 1))
+}
+
+fn vote(groups: Vec<Group>) -> (Vec<TextLine>, Vec<LineReport>) {
+    groups
+        .into_iter()
+        .map(|group| {
+            let normalized: Vec<String> = group.iter().map(|(_, l)| normalize(&l.text)).collect();
+            let mut tallies: Vec<(&String, usize)> = Vec::new();
+            for text in &normalized {
+                match tallies.iter_mut().find(|(t, _)| *t == text) {
+                    Some((_, votes)) => *votes += 1,
+                    None => tallies.push((text, 1)),
+                }
+            }
+            let most_votes = tallies.iter().map(|(_, votes)| *votes).max().unwrap_or(0);
+            let leaders: Vec<&String> = tallies
+                .iter()
+                .filter(|(_, votes)| *votes == most_votes)
+                .map(|(text, _)| *text)
+                .collect();
+            let majority = (most_votes > 1 && leaders.len() == 1).then(|| leaders[0]);
+
+            // among the candidates that are still in, the most confident
+            // wins, and then the earliest engine
+            let winner = (0..group.len())
+                .filter(|i| majority.is_none_or(|m| normalized[*i] == *m))
+                .fold(None, |best: Option<usize>, i| match best {
+                    Some(b) if confidence(group[b].1) >= confidence(group[i].1) => Some(b),
+                    _ => Some(i),
+                })
+                .unwrap_or(0);
+
+            let (engine, line) = group[winner];
+            let report = LineReport {
+                text: line.text.clone(),
+                engine: engine.to_string(),
+                decided_by: if majority.is_some() {
+                    "majority"
+                } else {
+                    "confidence"
+                }
+                .to_string(),
+                candidates: group
+                    .iter()
+                    .map(|(engine, line)| Candidate {
+                        engine: engine.to_string(),
+                        text: line.text.clone(),
+                        confidence: line.confidence(),
+                    })
+                    .collect(),
+            };
+            (line.clone(), report)
+        })
+        .unzip()
+}
+
+// engines without a confidence (google) lose against the ones that have one
+fn confidence(line: &TextLine) -> f32 {
+    line.confidence().unwrap_or(0.0)
+}
+
+fn normalize(text: &str) -> String {
+    text.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+#[cfg(test)]
+mod test {
+
+    extern crate wasm_bindgen_test;
+
+    use wasm_bindgen_test::*;
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    use super::Ensemble;
+    use crate::layout::{TextLine, WordBox};
+    use crate::ocr::{Document, OcrEngine};
+    use async_trait::async_trait;
+    use futures::executor::block_on;
+
+    // text, top, confidence
+    type FixedLine = (&'static str, u32, Option<f32>);
+
+    // answers with fixed lines
+    struct Fixed(Result<Vec<FixedLine>, &'static str>);
+
+    #[async_trait(?Send)]
+    impl OcrEngine for Fixed {
+        async fn recognize(&self, _img_data: &str) -> Result<Document, String> {
+            let lines = self.0.clone()?;
+            let lines: Vec<TextLine> = lines
+                .into_iter()
+                .map(|(text, top, confidence)| {
+                    let word = WordBox {
+                        text: text.to_string(),
+                        left: 10,
+                        top,
+                        width: 100,
+                        height: 30,
+                        confidence,
+                    };
+                    TextLine::from_words(text, vec![word])
+                })
+                .collect();
+            Ok(Document {
+                text: String::new(),
+                lines,
+            })
+        }
+    }
+
+    fn ensemble(engines: Vec<(&str, Fixed)>) -> Ensemble {
+        engines
+            .into_iter()
+            .fold(Ensemble::new(), |ensemble, (name, engine)| {
+                ensemble.with_engine(name, Box::new(engine))
+            })
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn majority_beats_confidence() {
+        let ensemble = ensemble(vec![
+            (
+                "google",
+                Fixed(Ok(vec![("Milch", 10, None), ("Brot", 50, None)])),
+            ),
+            (
+                "azure",
+                Fixed(Ok(vec![("Milch", 12, Some(0.7)), ("Brat", 52, Some(0.6))])),
+            ),
+            (
+                "textract",
+                Fixed(Ok(vec![("Mulch", 8, Some(0.99)), ("Brot", 49, Some(0.9))])),
+            ),
+        ]);
+
+        let (document, report) =
+            block_on(ensemble.recognize_with_report("img")).expect("test fails: engines answered");
+
+        assert_eq!("Milch\nBrot", document.text);
+        assert_eq!("azure", report.lines[0].engine);
+        assert_eq!("majority", report.lines[0].decided_by);
+        assert_eq!(3, report.lines[0].candidates.len());
+        assert_eq!("textract", report.lines[1].engine);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn without_agreement_the_most_confident_wins() {
+        let ensemble = ensemble(vec![
+            ("google", Fixed(Ok(vec![("Tomoten", 10, None)]))),
+            ("azure", Fixed(Ok(vec![("Tomaten", 10, Some(0.8))]))),
+        ]);
+
+        let (document, report) =
+            block_on(ensemble.recognize_with_report("img")).expect("test fails: engines answered");
+
+        assert_eq!("Tomaten", document.text);
+        assert_eq!("confidence", report.lines[0].decided_by);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn lines_only_one_engine_saw_are_kept_and_failures_reported() {
+        let ensemble = ensemble(vec![
+            (
+                "google",
+                Fixed(Ok(vec![("Eier", 10, None), ("Salz", 200, None)])),
+            ),
+            ("azure", Fixed(Err("Access denied"))),
+            ("textract", Fixed(Ok(vec![("Eier", 12, Some(0.9))]))),
+        ]);
+
+        let (document, report) = block_on(ensemble.recognize_with_report("img"))
+            .expect("test fails: two engines answered");
+
+        assert_eq!("Eier\nSalz", document.text);
+        assert_eq!(1, report.lines[1].candidates.len());
+        assert_eq!(
+            vec![("azure".to_string(), "Access denied".to_string())],
+            report.errors
+        );
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn fails_if_every_engine_fails() {
+        let ensemble = ensemble(vec![("azure", Fixed(Err("Access denied")))]);
+        assert!(block_on(ensemble.recognize_with_report("img")).is_err());
+    }
+}
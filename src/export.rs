@@ -0,0 +1,345 @@
+use crate::checkbox::CheckboxState;
+use crate::ical::{self, Uids};
+use crate::parser::ListItem;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Deserializer};
+
+/// The text formats the items can be written as, instead of creating tasks.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    // github-style checklist, sections become headings
+    #[default]
+    Markdown,
+    // TODO entries, the hierarchy is the number of stars
+    Org,
+    // one task per line, subtasks are flattened (todo.txt has no hierarchy)
+    TodoTxt,
+    Csv,
+    Json,
+    // an icalendar file of VTODOs, reproducible with `ExportConfig`
+    Ics,
+}
+
+/// Makes the `.ics` export byte-stable, the other formats have no ids or times.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ExportConfig {
+    // name (or id) of the list, set the UIDs are derived from it and the
+    // items instead of being random, see `ical::Uids::for_list`
+    pub list: Option<String>,
+    // the DTSTAMP of the todos (rfc 3339), now if not set
+    #[serde(deserialize_with = "rfc3339")]
+    pub stamp: Option<DateTime<Utc>>,
+}
+
+fn rfc3339<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<DateTime<Utc>>, D::Error> {
+    Option::<String>::deserialize(deserializer)?
+        .map(|stamp| {
+            DateTime::parse_from_rfc3339(&stamp)
+                .map(|stamp| stamp.with_timezone(&Utc))
+                .map_err(serde::de::Error::custom)
+        })
+        .transpose()
+}
+
+impl ExportFormat {
+    /// Accepts the serde names ("todo_txt") and the usual file extensions ("md", "txt").
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        match name.trim().to_lowercase().as_str() {
+            "markdown" | "md" => Ok(ExportFormat::Markdown),
+            "org" => Ok(ExportFormat::Org),
+            "todo_txt" | "todotxt" | "txt" => Ok(ExportFormat::TodoTxt),
+            "csv" => Ok(ExportFormat::Csv),
+            "json" => Ok(ExportFormat::Json),
+            "ics" | "ical" | "icalendar" => Ok(ExportFormat::Ics),
+            other => Err(format!("unknown export format: {}", other)),
+        }
+    }
+}
+
+pub fn render(items: &[ListItem], format: ExportFormat, config: &ExportConfig) -> String {
+    match format {
+        ExportFormat::Markdown => markdown(items),
+        ExportFormat::Org => org(items),
+        ExportFormat::TodoTxt => todo_txt(items),
+        ExportFormat::Csv => csv(items),
+        ExportFormat::Json => serde_json::to_string_pretty(items).unwrap_or_default(),
+        ExportFormat::Ics => {
+            let uids = config.list.as_deref().map_or(Uids::Random, Uids::for_list);
+            ical::export_items(items, uids, config.stamp.unwrap_or_else(Utc::now))
+        }
+    }
+}
+
+// how many parents the item has
+fn depth(items: &[ListItem], index: usize) -> usize {
+    let mut depth = 0;
+    let mut current = items[index].parent;
+    while let Some(parent) = current.filter(|p| *p < items.len() && depth < items.len()) {
+        depth += 1;
+        current = items[parent].parent;
+    }
+    depth
+}
+
+fn is_checked(item: &ListItem) -> bool {
+    item.checkbox == Some(CheckboxState::Checked)
+}
+
+// a new heading is written whenever the section changes
+fn section_change(items: &[ListItem], index: usize) -> Option<&str> {
+    let section = items[index].section.as_deref()?;
+    let previous = index
+        .checked_sub(1)
+        .and_then(|i| items[i].section.as_deref());
+    (previous != Some(section)).then_some(section)
+}
+
+fn markdown(items: &[ListItem]) -> String {
+    let mut out = String::new();
+    for (index, item) in items.iter().enumerate() {
+        if let Some(section) = section_change(items, index) {
+            if !out.is_empty() {
+                out.push('\n');
+            }
+            out.push_str(&format!("## {}\n\n", section));
+        }
+        let indent = "  ".repeat(depth(items, index));
+        let check = if is_checked(item) { 'x' } else { ' ' };
+        out.push_str(&format!("{}- [{}] {}", indent, check, item.content));
+        if let Some(priority) = item.priority.filter(|p| *p > 1) {
+            out.push_str(&format!(" (p{})", 5 - priority));
+        }
+        if let Some(due) = &item.due {
+            out.push_str(&format!(" (due {})", due));
+        }
+        for label in &item.labels {
+            out.push_str(&format!(" #{}", label));
+        }
+        out.push('\n');
+        for line in item.description.iter().flat_map(|d| d.lines()) {
+            out.push_str(&format!("{}  {}\n", indent, line));
+        }
+    }
+    out
+}
+
+fn org(items: &[ListItem]) -> String {
+    let mut out = String::new();
+    for (index, item) in items.iter().enumerate() {
+        if let Some(section) = section_change(items, index) {
+            out.push_str(&format!("* {}\n", section));
+        }
+        let level = depth(items, index) + 1 + usize::from(item.section.is_some());
+        let keyword = if is_checked(item) { "DONE" } else { "TODO" };
+        out.push_str(&format!("{} {}", "*".repeat(level), keyword));
+        if let Some(cookie) = item.priority.and_then(org_priority) {
+            out.push_str(&format!(" [#{}]", cookie));
+        }
+        out.push_str(&format!(" {}", item.content));
+        if !item.labels.is_empty() {
+            out.push_str(&format!(" :{}:", item.labels.join(":")));
+        }
+        out.push('\n');
+        if let Some(due) = &item.due {
+            let weekday = chrono::NaiveDate::parse_from_str(due, "%Y-%m-%d")
+                .map(|date| format!(" {}", date.format("%a")))
+                .unwrap_or_default();
+            out.push_str(&format!("DEADLINE: <{}{}>\n", due, weekday));
+        }
+        for line in item.description.iter().flat_map(|d| d.lines()) {
+            out.push_str(&format!("{}\n", line));
+        }
+    }
+    out
+}
+
+// todoist 4 (urgent) is [#A], normal tasks get no cookie
+fn org_priority(priority: u8) -> Option<char> {
+    match priority {
+        4 => Some('A'),
+        3 => Some('B'),
+        2 => Some('C'),
+        _ => None,
+    }
+}
+
+fn todo_txt(items: &[ListItem]) -> String {
+    let mut out = String::new();
+    for item in items {
+        let mut parts: Vec<String> = Vec::new();
+        if is_checked(item) {
+            parts.push("x".to_string());
+        }
+        if let Some(cookie) = item.priority.and_then(org_priority) {
+            parts.push(format!("({})", cookie));
+        }
+        parts.push(item.content.clone());
+        if let Some(section) = &item.section {
+            parts.push(format!(
+                "+{}",
+                section.split_whitespace().collect::<Vec<_>>().join("_")
+            ));
+        }
+        parts.extend(item.labels.iter().map(|label| format!("@{}", label)));
+        if let Some(due) = &item.due {
+            parts.push(format!("due:{}", due));
+        }
+        out.push_str(&parts.join(" "));
+        out.push('\n');
+    }
+    out
+}
+
+const CSV_HEADER: &str = "content,priority,due,labels,section,parent,checked,description";
+
+// parent is the (zero based) row of the parent item, labels are separated by spaces
+fn csv(items: &[ListItem]) -> String {
+    let mut out = format!("{}\r\n", CSV_HEADER);
+    for item in items {
+        let row = [
+            item.content.clone(),
+            item.priority.map(|p| p.to_string()).unwrap_or_default(),
+            item.due.clone().unwrap_or_default(),
+            item.labels.join(" "),
+            item.section.clone().unwrap_or_default(),
+            item.parent.map(|p| p.to_string()).unwrap_or_default(),
+            is_checked(item).to_string(),
+            item.description.clone().unwrap_or_default(),
+        ];
+        let fields: Vec<String> = row.iter().map(|field| csv_field(field)).collect();
+        out.push_str(&fields.join(","));
+        out.push_str("\r\n");
+    }
+    out
+}
+
+// quoted as in rfc 4180, if needed
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    extern crate wasm_bindgen_test;
+
+    use wasm_bindgen_test::*;
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    use super::{render, ExportConfig, ExportFormat};
+    use crate::checkbox::CheckboxState;
+    use crate::options::Options;
+    use crate::parser::ListItem;
+
+    fn items() -> Vec<ListItem> {
+        vec![
+            ListItem {
+                content: "Party vorbereiten".to_string(),
+                priority: Some(4),
+                due: Some("2024-06-01".to_string()),
+                section: Some("Samstag".to_string()),
+                ..ListItem::default()
+            },
+            ListItem {
+                content: "Chips, Salzstangen".to_string(),
+                parent: Some(0),
+                labels: vec!["edeka".to_string()],
+                section: Some("Samstag".to_string()),
+                checkbox: Some(CheckboxState::Checked),
+                ..ListItem::default()
+            },
+            ListItem {
+                content: "Blumen gießen".to_string(),
+                description: Some("die im \"Bad\" auch".to_string()),
+                ..ListItem::default()
+            },
+        ]
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn markdown_is_a_nested_checklist() {
+        assert_eq!(
+            "## Samstag\n\n\
+             - [ ] Party vorbereiten (p1) (due 2024-06-01)\n  \
+             - [x] Chips, Salzstangen #edeka\n\
+             - [ ] Blumen gießen\n  \
+             die im \"Bad\" auch\n",
+            render(&items(), ExportFormat::Markdown, &ExportConfig::default())
+        );
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn org_entries_have_levels_and_deadlines() {
+        assert_eq!(
+            "* Samstag\n\
+             ** TODO [#A] Party vorbereiten\n\
+             DEADLINE: <2024-06-01 Sat>\n\
+             *** DONE Chips, Salzstangen :edeka:\n\
+             * TODO Blumen gießen\n\
+             die im \"Bad\" auch\n",
+            render(&items(), ExportFormat::Org, &ExportConfig::default())
+        );
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn todo_txt_is_flat() {
+        assert_eq!(
+            "(A) Party vorbereiten +Samstag due:2024-06-01\n\
+             x Chips, Salzstangen +Samstag @edeka\n\
+             Blumen gießen\n",
+            render(&items(), ExportFormat::TodoTxt, &ExportConfig::default())
+        );
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn csv_fields_are_quoted() {
+        assert_eq!(
+            "content,priority,due,labels,section,parent,checked,description\r\n\
+             Party vorbereiten,4,2024-06-01,,Samstag,,false,\r\n\
+             \"Chips, Salzstangen\",,,edeka,Samstag,0,true,\r\n\
+             Blumen gießen,,,,,,false,\"die im \"\"Bad\"\" auch\"\r\n",
+            render(&items(), ExportFormat::Csv, &ExportConfig::default())
+        );
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn json_keeps_the_item_model() {
+        let json = render(&items(), ExportFormat::Json, &ExportConfig::default());
+        let parsed: Vec<ListItem> =
+            serde_json::from_str(&json).expect("test fails: export must be valid json");
+        assert_eq!(items(), parsed);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn ics_is_byte_stable_with_list_and_stamp() {
+        let options = Options::from_json(
+            r#"{"export": {"list": "Samstag", "stamp": "2024-05-20T10:30:00+02:00"}}"#,
+        )
+        .expect("test fails: options must parse");
+
+        let ics = render(&items(), ExportFormat::Ics, &options.export);
+
+        assert!(ics.contains("DTSTAMP:20240520T083000Z\r\n"));
+        assert_eq!(ics, render(&items(), ExportFormat::Ics, &options.export));
+        assert_ne!(
+            render(&items(), ExportFormat::Ics, &ExportConfig::default()),
+            render(&items(), ExportFormat::Ics, &ExportConfig::default())
+        );
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn formats_are_found_by_name() {
+        assert_eq!(Ok(ExportFormat::Markdown), ExportFormat::from_name("md"));
+        assert_eq!(
+            Ok(ExportFormat::TodoTxt),
+            ExportFormat::from_name("todo_txt")
+        );
+        assert!(ExportFormat::from_name("pdf").is_err());
+    }
+}
@@ -0,0 +1,353 @@
+use crate::auth;
+use crate::http::{DefaultClient, HttpClient, HttpRequest, HttpResponse};
+use crate::secret::Secret;
+use crate::types::google_oauth::{CodeExchange, ErrorResponse, Refresh, TokenResponse};
+use base64::{CharacterSet::UrlSafe, Config};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+const CONSENT_URL: &str = "https://accounts.google.com/o/oauth2/v2/auth";
+const TOKEN_URL: &str = "https://oauth2.googleapis.com/token";
+pub const VISION_SCOPE: &str = auth::CLOUD_VISION_SCOPE;
+// tokens this close to their expiry are refreshed already
+const EXPIRY_MARGIN_SECS: i64 = 60;
+
+/// The proof key of one login (rfc 7636): the verifier stays with the
+/// client, google only sees the challenge until the code is exchanged.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Pkce {
+    pub verifier: String,
+    pub challenge: String,
+}
+
+impl Pkce {
+    pub fn new() -> Result<Self, String> {
+        let mut random = [0u8; 32];
+        getrandom::getrandom(&mut random).map_err(|e| e.to_string())?;
+        Ok(Pkce::from_verifier(&base64_url(&random)))
+    }
+
+    pub fn from_verifier(verifier: &str) -> Self {
+        Pkce {
+            verifier: verifier.to_string(),
+            challenge: base64_url(&hmac_sha256::Hash::hash(verifier.as_bytes())),
+        }
+    }
+}
+
+fn base64_url(bytes: &[u8]) -> String {
+    base64::encode_config(bytes, Config::new(UrlSafe, false))
+}
+
+/// Everything the client has to keep between the redirect to google
+/// and the exchange of the code.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Consent {
+    pub url: String,
+    pub state: String,
+    pub verifier: String,
+}
+
+/// The tokens of a user, kept by the client (e.g. as json in local storage).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UserTokens {
+    pub access_token: Secret,
+    pub refresh_token: Option<Secret>,
+    // unix seconds
+    pub expires_at: i64,
+    pub scope: String,
+}
+
+impl UserTokens {
+    pub fn needs_refresh(&self, now: DateTime<Utc>) -> bool {
+        now.timestamp() + EXPIRY_MARGIN_SECS >= self.expires_at
+    }
+}
+
+/// Google's oauth for installed apps and single page apps (with pkce), so
+/// that users authorise with their own google account instead of every
+/// client shipping the private key of a service-account.
+pub struct GoogleUserAuth<C> {
+    client_id: String,
+    // installed apps get a secret that is not really secret, web apps none
+    client_secret: Option<String>,
+    redirect_uri: String,
+    client: C,
+    pub consent_url: String,
+    pub token_url: String,
+    pub clock: fn() -> DateTime<Utc>,
+}
+
+impl GoogleUserAuth<DefaultClient> {
+    pub fn new(client_id: &str, client_secret: Option<&str>, redirect_uri: &str) -> Self {
+        GoogleUserAuth::with_client(
+            client_id,
+            client_secret,
+            redirect_uri,
+            DefaultClient::default(),
+        )
+    }
+}
+
+impl<C: HttpClient> GoogleUserAuth<C> {
+    pub fn with_client(
+        client_id: &str,
+        client_secret: Option<&str>,
+        redirect_uri: &str,
+        client: C,
+    ) -> Self {
+        GoogleUserAuth {
+            client_id: client_id.to_string(),
+            client_secret: client_secret.map(str::to_string),
+            redirect_uri: redirect_uri.to_string(),
+            client,
+            consent_url: CONSENT_URL.to_string(),
+            token_url: TOKEN_URL.to_string(),
+            clock: Utc::now,
+        }
+    }
+
+    /// Asks for offline access, so that a refresh token comes with the
+    /// first exchange.
+    pub fn consent(&self, scopes: &[&str]) -> Result<Consent, String> {
+        self.consent_with(scopes, &Pkce::new()?, &uuid::Uuid::new_v4().to_string())
+    }
+
+    pub fn consent_with(
+        &self,
+        scopes: &[&str],
+        pkce: &Pkce,
+        state: &str,
+    ) -> Result<Consent, String> {
+        let scope = scopes.join(" ");
+        let query = serde_urlencoded::to_string([
+            ("client_id", self.client_id.as_str()),
+            ("redirect_uri", &self.redirect_uri),
+            ("response_type", "code"),
+            ("scope", &scope),
+            ("code_challenge", &pkce.challenge),
+            ("code_challenge_method", "S256"),
+            ("state", state),
+            ("access_type", "offline"),
+            ("prompt", "consent"),
+        ])
+        .map_err(|e| e.to_string())?;
+        Ok(Consent {
+            url: format!("{}?{}", self.consent_url, query),
+            state: state.to_string(),
+            verifier: pkce.verifier.clone(),
+        })
+    }
+
+    /// Trades the code of the redirect for tokens, if the redirect carries
+    /// the state of the consent.
+    pub async fn exchange(
+        &self,
+        code: &str,
+        returned_state: &str,
+        consent: &Consent,
+    ) -> Result<UserTokens, String> {
+        if returned_state.is_empty() || returned_state != consent.state {
+            return Err("the state of the redirect does not match".to_string());
+        }
+        let form = CodeExchange {
+            client_id: &self.client_id,
+            client_secret: self.client_secret.as_deref(),
+            code,
+            code_verifier: &consent.verifier,
+            grant_type: "authorization_code",
+            redirect_uri: &self.redirect_uri,
+        };
+        let response = self.post_form(&form).await?;
+        Ok(self.tokens(response, None))
+    }
+
+    /// Returns the tokens as they are while the access token is still valid,
+    /// otherwise a refreshed copy (which keeps the refresh token).
+    pub async fn fresh(&self, tokens: &UserTokens) -> Result<UserTokens, String> {
+        if !tokens.needs_refresh((self.clock)()) {
+            return Ok(tokens.clone());
+        }
+        self.refresh(tokens).await
+    }
+
+    pub async fn refresh(&self, tokens: &UserTokens) -> Result<UserTokens, String> {
+        let refresh_token = tokens
+            .refresh_token
+            .as_ref()
+            .ok_or("no refresh token, the user has to log in again")?;
+        let form = Refresh {
+            client_id: &self.client_id,
+            client_secret: self.client_secret.as_deref(),
+            refresh_token: refresh_token.expose(),
+            grant_type: "refresh_token",
+        };
+        let response = self.post_form(&form).await?;
+        Ok(self.tokens(response, Some(refresh_token)))
+    }
+
+    async fn post_form<F: Serialize>(&self, form: &F) -> Result<TokenResponse, String> {
+        let body = serde_urlencoded::to_string(form).map_err(|e| e.to_string())?;
+        let request = HttpRequest::new("POST", &self.token_url)
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .body(body.into_bytes());
+        let response = self.client.send(request).await?;
+        if !response.is_success() {
+            return Err(token_error(&response));
+        }
+        response.json()
+    }
+
+    fn tokens(&self, response: TokenResponse, refresh_token: Option<&Secret>) -> UserTokens {
+        UserTokens {
+            access_token: response.access_token,
+            refresh_token: response.refresh_token.or_else(|| refresh_token.cloned()),
+            expires_at: (self.clock)().timestamp() + response.expires_in,
+            scope: response.scope,
+        }
+    }
+}
+
+fn token_error(response: &HttpResponse) -> String {
+    match response.json::<ErrorResponse>() {
+        Ok(e) => match e.error_description {
+            Some(description) => format!("{}: {}", e.error, description),
+            None => e.error,
+        },
+        Err(_) => format!(
+            "the token endpoint answered with status {}",
+            response.status
+        ),
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod test {
+
+    use super::{GoogleUserAuth, Pkce, UserTokens, VISION_SCOPE};
+    use crate::http::{HttpResponse, MockServer, NativeClient, Replay};
+    use crate::secret::Secret;
+    use chrono::{DateTime, TimeZone, Utc};
+    use futures::executor::block_on;
+
+    fn fixed_clock() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 5, 20, 8, 30, 0).unwrap()
+    }
+
+    fn auth(server: &MockServer) -> GoogleUserAuth<NativeClient> {
+        let mut auth = GoogleUserAuth::with_client(
+            "client.apps.googleusercontent.com",
+            None,
+            "http://localhost:8080/callback",
+            NativeClient::default(),
+        );
+        auth.token_url = format!("{}/token", server.url);
+        auth.clock = fixed_clock;
+        auth
+    }
+
+    fn tokens(expires_at: i64, refresh_token: Option<&str>) -> UserTokens {
+        UserTokens {
+            access_token: "ya29.old".into(),
+            refresh_token: refresh_token.map(Secret::from),
+            expires_at,
+            scope: VISION_SCOPE.to_string(),
+        }
+    }
+
+    #[test]
+    fn challenge_is_the_hashed_verifier() {
+        // rfc 7636, appendix b
+        let pkce = Pkce::from_verifier("dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk");
+        assert_eq!(
+            "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM",
+            pkce.challenge
+        );
+
+        let random = Pkce::new().expect("test fails: random bytes are available");
+        assert_eq!(43, random.verifier.len());
+        assert_ne!(random, Pkce::new().unwrap());
+    }
+
+    #[test]
+    fn consent_url_asks_for_offline_access_with_the_challenge() {
+        let auth =
+            GoogleUserAuth::with_client("id", None, "http://localhost/cb", Replay::new(vec![]));
+        let pkce = Pkce::from_verifier("dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk");
+
+        let consent = auth
+            .consent_with(&[VISION_SCOPE], &pkce, "xyz")
+            .expect("test fails: query must encode");
+
+        assert_eq!(
+            "https://accounts.google.com/o/oauth2/v2/auth?client_id=id\
+             &redirect_uri=http%3A%2F%2Flocalhost%2Fcb&response_type=code\
+             &scope=https%3A%2F%2Fwww.googleapis.com%2Fauth%2Fcloud-vision\
+             &code_challenge=E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM&code_challenge_method=S256\
+             &state=xyz&access_type=offline&prompt=consent",
+            consent.url
+        );
+        assert_eq!(pkce.verifier, consent.verifier);
+    }
+
+    #[test]
+    fn code_is_exchanged_with_the_verifier() {
+        let server = MockServer::start(vec![HttpResponse::with_json(
+            200,
+            r#"{"access_token": "ya29.new", "expires_in": 3599, "refresh_token": "1//refresh",
+                "scope": "https://www.googleapis.com/auth/cloud-vision", "token_type": "Bearer"}"#,
+        )]);
+        let auth = auth(&server);
+        let consent = auth
+            .consent_with(&[VISION_SCOPE], &Pkce::from_verifier("verifier"), "xyz")
+            .unwrap();
+
+        let tokens = block_on(auth.exchange("4/code", "xyz", &consent))
+            .expect("test fails: mock token endpoint answers");
+        let requests = server.requests();
+
+        assert_eq!(Some(Secret::from("1//refresh")), tokens.refresh_token);
+        assert_eq!(fixed_clock().timestamp() + 3599, tokens.expires_at);
+        assert_eq!(
+            "client_id=client.apps.googleusercontent.com&code=4%2Fcode&code_verifier=verifier\
+             &grant_type=authorization_code&redirect_uri=http%3A%2F%2Flocalhost%3A8080%2Fcallback",
+            String::from_utf8_lossy(&requests[0].body)
+        );
+    }
+
+    #[test]
+    fn expired_tokens_are_refreshed_and_keep_the_refresh_token() {
+        let server = MockServer::start(vec![HttpResponse::with_json(
+            200,
+            r#"{"access_token": "ya29.new", "expires_in": 3599, "token_type": "Bearer"}"#,
+        )]);
+        let auth = auth(&server);
+        let valid = tokens(fixed_clock().timestamp() + 600, Some("1//refresh"));
+        let expired = tokens(fixed_clock().timestamp() + 30, Some("1//refresh"));
+
+        assert_eq!(valid, block_on(auth.fresh(&valid)).unwrap());
+        let refreshed = block_on(auth.fresh(&expired)).expect("test fails: mock answers");
+        let requests = server.requests();
+
+        assert_eq!(1, requests.len());
+        assert_eq!("ya29.new", refreshed.access_token.expose());
+        assert_eq!(Some(Secret::from("1//refresh")), refreshed.refresh_token);
+        assert!(String::from_utf8_lossy(&requests[0].body).contains("grant_type=refresh_token"));
+    }
+
+    #[test]
+    fn token_errors_are_reported() {
+        let server = MockServer::start(vec![HttpResponse::with_json(
+            400,
+            r#"{"error": "invalid_grant", "error_description": "Token has been expired or revoked."}"#,
+        )]);
+        let auth = auth(&server);
+
+        let error = block_on(auth.refresh(&tokens(0, Some("1//revoked"))))
+            .expect_err("test fails: the endpoint refused");
+        server.requests();
+
+        assert_eq!("invalid_grant: Token has been expired or revoked.", error);
+        assert!(block_on(auth.refresh(&tokens(0, None))).is_err());
+    }
+}
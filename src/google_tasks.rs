@@ -0,0 +1,226 @@
+use crate::http::{DefaultClient, HttpClient, HttpRequest};
+use crate::secret::Secret;
+use crate::sink::{SinkProject, SinkTask, TaskDraft, TaskSink};
+use crate::types::google_tasks::{Page, Task, TaskList};
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+const TASKS_URL: &str = "https://tasks.googleapis.com/tasks/v1";
+
+/// Google Tasks, authenticated with an oauth access token of the user
+/// (scope `https://www.googleapis.com/auth/tasks`).
+pub struct GoogleTasks<C> {
+    token: Secret,
+    client: C,
+    pub base_url: String,
+    // new tasks go to the top, so every task is placed after the
+    // last one created under the same parent ("" for the top level)
+    last_sibling: RefCell<HashMap<String, String>>,
+}
+
+impl GoogleTasks<DefaultClient> {
+    pub fn new(token: &str) -> Self {
+        GoogleTasks::with_client(token, DefaultClient::default())
+    }
+}
+
+impl<C: HttpClient> GoogleTasks<C> {
+    pub fn with_client(token: &str, client: C) -> Self {
+        GoogleTasks {
+            token: Secret::from(token),
+            client,
+            base_url: TASKS_URL.to_string(),
+            last_sibling: RefCell::new(HashMap::new()),
+        }
+    }
+
+    fn request(&self, method: &str, url: &str) -> HttpRequest {
+        HttpRequest::new(method, url).bearer(&self.token)
+    }
+
+    async fn post<T: DeserializeOwned>(&self, url: &str, body: Vec<u8>) -> Result<T, String> {
+        let request = self
+            .request("POST", url)
+            .header("Content-Type", "application/json")
+            .body(body);
+        self.client.send(request).await?.into_json()
+    }
+
+    // follows the nextPageToken of every page
+    async fn get_all<T: DeserializeOwned>(&self, url: &str) -> Result<Vec<T>, String> {
+        let mut all = Vec::new();
+        let mut page_token: Option<String> = None;
+        loop {
+            let page_url = match &page_token {
+                Some(token) => format!("{}?{}", url, query(&[("pageToken", token)])?),
+                None => url.to_string(),
+            };
+            let page: Page<T> = self
+                .client
+                .send(self.request("GET", &page_url))
+                .await?
+                .into_json()?;
+            all.extend(page.items);
+            match page.next_page_token {
+                Some(token) => page_token = Some(token),
+                None => return Ok(all),
+            }
+        }
+    }
+}
+
+fn query(pairs: &[(&str, &str)]) -> Result<String, String> {
+    serde_urlencoded::to_string(pairs).map_err(|e| e.to_string())
+}
+
+#[async_trait(?Send)]
+impl<C: HttpClient> TaskSink for GoogleTasks<C> {
+    async fn list_projects(&self) -> Result<Vec<SinkProject>, String> {
+        let lists: Vec<TaskList> = self
+            .get_all(&format!("{}/users/@me/lists", self.base_url))
+            .await?;
+        Ok(lists
+            .into_iter()
+            .map(|list| SinkProject {
+                id: list.id,
+                name: list.title,
+            })
+            .collect())
+    }
+
+    async fn ensure_project(&self, name: &str) -> Result<String, String> {
+        let lists = self.list_projects().await?;
+        if let Some(list) = lists.iter().find(|l| l.name.eq_ignore_ascii_case(name)) {
+            return Ok(list.id.clone());
+        }
+        let list = TaskList {
+            title: name.to_string(),
+            ..TaskList::default()
+        };
+        let body = serde_json::to_vec(&list).map_err(|e| e.to_string())?;
+        let created: TaskList = self
+            .post(&format!("{}/users/@me/lists", self.base_url), body)
+            .await?;
+        Ok(created.id)
+    }
+
+    async fn create_task(&self, project_id: &str, draft: &TaskDraft) -> Result<String, String> {
+        let parent = draft.parent_id.clone().unwrap_or_default();
+        let previous = self.last_sibling.borrow().get(&parent).cloned();
+
+        let mut params: Vec<(&str, &str)> = Vec::new();
+        if !parent.is_empty() {
+            params.push(("parent", &parent));
+        }
+        if let Some(previous) = &previous {
+            params.push(("previous", previous));
+        }
+        let mut url = format!("{}/lists/{}/tasks", self.base_url, project_id);
+        if !params.is_empty() {
+            url = format!("{}?{}", url, query(&params)?);
+        }
+
+        let task = Task {
+            title: draft.item.content.clone(),
+            notes: draft.item.description.clone(),
+            status: draft.completed.then(|| "completed".to_string()),
+            ..Task::default()
+        };
+        let body = serde_json::to_vec(&task).map_err(|e| e.to_string())?;
+        let created: Task = self.post(&url, body).await?;
+        self.last_sibling
+            .borrow_mut()
+            .insert(parent, created.id.clone());
+        Ok(created.id)
+    }
+
+    async fn find_tasks(&self, project_id: &str) -> Result<Vec<SinkTask>, String> {
+        let tasks: Vec<Task> = self
+            .get_all(&format!("{}/lists/{}/tasks", self.base_url, project_id))
+            .await?;
+        Ok(tasks
+            .into_iter()
+            .map(|task| SinkTask {
+                id: task.id,
+                content: task.title,
+            })
+            .collect())
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod test {
+
+    use super::GoogleTasks;
+    use crate::http::{HttpResponse, MockServer, NativeClient};
+    use crate::options::Options;
+    use crate::parser::ListItem;
+    use crate::sink::{upload, TaskSink};
+    use futures::executor::block_on;
+
+    fn sink(server: &MockServer) -> GoogleTasks<NativeClient> {
+        let mut sink = GoogleTasks::with_client("google-token", NativeClient::default());
+        sink.base_url = server.url.clone();
+        sink
+    }
+
+    #[test]
+    fn tasks_keep_their_order_and_parents() {
+        let server = MockServer::start(vec![
+            HttpResponse::with_json(200, r#"{"id": "t1", "title": "Party"}"#),
+            HttpResponse::with_json(200, r#"{"id": "t2", "title": "Chips"}"#),
+            HttpResponse::with_json(200, r#"{"id": "t3", "title": "Cola"}"#),
+            HttpResponse::with_json(200, r#"{"id": "t4", "title": "Brot"}"#),
+        ]);
+        let sink = sink(&server);
+        let item = |content: &str, parent: Option<usize>| ListItem {
+            content: content.to_string(),
+            parent,
+            ..ListItem::default()
+        };
+        let items = vec![
+            item("Party", None),
+            item("Chips", Some(0)),
+            item("Cola", Some(0)),
+            item("Brot", None),
+        ];
+
+        block_on(upload(&sink, "L1", items, &Options::default()))
+            .expect("test fails: mock server answers");
+        let requests = server.requests();
+
+        let urls: Vec<&str> = requests.iter().map(|r| r.url.as_str()).collect();
+        assert_eq!(
+            vec![
+                "/lists/L1/tasks",
+                "/lists/L1/tasks?parent=t1",
+                "/lists/L1/tasks?parent=t1&previous=t2",
+                "/lists/L1/tasks?previous=t1",
+            ],
+            urls
+        );
+    }
+
+    #[test]
+    fn existing_tasks_are_read_from_every_page() {
+        let server = MockServer::start(vec![
+            HttpResponse::with_json(
+                200,
+                r#"{"items": [{"id": "t1", "title": "Milch"}], "nextPageToken": "p2"}"#,
+            ),
+            HttpResponse::with_json(200, r#"{"items": [{"id": "t2", "title": "Eier"}]}"#),
+        ]);
+        let sink = sink(&server);
+
+        let tasks = block_on(sink.find_tasks("L1")).expect("test fails: mock server answers");
+        let requests = server.requests();
+
+        assert_eq!(
+            vec!["Milch", "Eier"],
+            tasks.iter().map(|t| t.content.as_str()).collect::<Vec<_>>()
+        );
+        assert_eq!("/lists/L1/tasks?pageToken=p2", requests[1].url);
+    }
+}
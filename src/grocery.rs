@@ -0,0 +1,607 @@
+use crate::parser::ListItem;
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct GroceryConfig {
+    pub mode: GroceryMode,
+    pub target: CategoryTarget,
+    // checked before the bundled table, so they can override it
+    pub categories: Vec<GroceryCategory>,
+    // turn off to only use `categories`
+    pub bundled: bool,
+    // the way through the store, unknown categories go last
+    pub aisle_order: Vec<String>,
+    // language code of the section or label names of the bundled categories
+    pub language: String,
+}
+
+impl Default for GroceryConfig {
+    fn default() -> Self {
+        GroceryConfig {
+            mode: GroceryMode::default(),
+            target: CategoryTarget::default(),
+            categories: Vec::new(),
+            bundled: true,
+            aisle_order: BUNDLED.iter().map(|(name, _)| name.to_string()).collect(),
+            language: "de".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GroceryMode {
+    Off,
+    // only when the "Einkaufsliste" project gets created
+    #[default]
+    ShoppingList,
+    Always,
+}
+
+/// How the category shows up in todoist.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CategoryTarget {
+    #[default]
+    Section,
+    Label,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct GroceryCategory {
+    pub name: String,
+    pub keywords: Vec<String>,
+}
+
+// german and english keywords, in the default aisle order
+const BUNDLED: &[(&str, &[&str])] = &[
+    (
+        "produce",
+        &[
+            "apfel",
+            "äpfel",
+            "apple",
+            "banane",
+            "banana",
+            "birne",
+            "pear",
+            "orange",
+            "zitrone",
+            "lemon",
+            "limette",
+            "lime",
+            "traube",
+            "grape",
+            "beere",
+            "berry",
+            "berries",
+            "kiwi",
+            "obst",
+            "fruit",
+            "gemüse",
+            "vegetable",
+            "tomate",
+            "tomato",
+            "gurke",
+            "cucumber",
+            "salat",
+            "lettuce",
+            "kartoffel",
+            "potato",
+            "zwiebel",
+            "onion",
+            "knoblauch",
+            "garlic",
+            "möhre",
+            "karotte",
+            "carrot",
+            "paprika",
+            "pepper",
+            "zucchini",
+            "pilz",
+            "champignon",
+            "mushroom",
+            "brokkoli",
+            "broccoli",
+            "spinat",
+            "spinach",
+            "avocado",
+            "kräuter",
+            "petersilie",
+            "basilikum",
+            "herbs",
+            "ingwer",
+            "ginger",
+            "lauch",
+        ],
+    ),
+    (
+        "bakery",
+        &[
+            "brot",
+            "bread",
+            "brötchen",
+            "semmel",
+            "roll",
+            "toast",
+            "baguette",
+            "croissant",
+            "brezel",
+            "pretzel",
+            "kuchen",
+            "cake",
+            "bagel",
+        ],
+    ),
+    (
+        "meat",
+        &[
+            "fleisch",
+            "meat",
+            "hack",
+            "mince",
+            "wurst",
+            "sausage",
+            "schinken",
+            "ham",
+            "salami",
+            "hähnchen",
+            "huhn",
+            "chicken",
+            "pute",
+            "turkey",
+            "rind",
+            "beef",
+            "schwein",
+            "pork",
+            "speck",
+            "bacon",
+            "fisch",
+            "fish",
+            "lachs",
+            "salmon",
+            "thunfisch",
+            "tuna",
+        ],
+    ),
+    (
+        "dairy",
+        &[
+            "milch",
+            "milk",
+            "joghurt",
+            "jogurt",
+            "yogurt",
+            "yoghurt",
+            "käse",
+            "cheese",
+            "butter",
+            "sahne",
+            "cream",
+            "quark",
+            "eier",
+            "ei",
+            "egg",
+            "eggs",
+            "margarine",
+            "mozzarella",
+            "feta",
+            "frischkäse",
+            "schmand",
+        ],
+    ),
+    (
+        "frozen",
+        &[
+            "tiefkühl",
+            "tk",
+            "frozen",
+            "pizza",
+            "eis",
+            "ice",
+            "pommes",
+            "fries",
+            "spinat-tk",
+        ],
+    ),
+    (
+        "pantry",
+        &[
+            "nudeln",
+            "pasta",
+            "spaghetti",
+            "reis",
+            "rice",
+            "mehl",
+            "flour",
+            "zucker",
+            "sugar",
+            "salz",
+            "salt",
+            "öl",
+            "oil",
+            "essig",
+            "vinegar",
+            "müsli",
+            "cereal",
+            "haferflocken",
+            "oats",
+            "honig",
+            "honey",
+            "marmelade",
+            "jam",
+            "dose",
+            "konserve",
+            "can",
+            "soße",
+            "sauce",
+            "ketchup",
+            "senf",
+            "mustard",
+            "gewürz",
+            "spice",
+            "kaffee",
+            "coffee",
+            "tee",
+            "tea",
+            "linsen",
+            "lentils",
+            "bohnen",
+            "beans",
+            "mais",
+            "corn",
+        ],
+    ),
+    (
+        "snacks",
+        &[
+            "chips",
+            "crisps",
+            "schokolade",
+            "chocolate",
+            "kekse",
+            "cookies",
+            "gummibärchen",
+            "süßigkeiten",
+            "sweets",
+            "candy",
+            "nüsse",
+            "nuts",
+            "cracker",
+            "popcorn",
+        ],
+    ),
+    (
+        "drinks",
+        &[
+            "wasser", "water", "saft", "juice", "cola", "limo", "limonade", "soda", "bier", "beer",
+            "wein", "wine", "sekt", "schorle",
+        ],
+    ),
+    (
+        "household",
+        &[
+            "spülmittel",
+            "dish",
+            "waschmittel",
+            "detergent",
+            "müllbeutel",
+            "bin",
+            "klopapier",
+            "toilettenpapier",
+            "toilet",
+            "küchenrolle",
+            "paper",
+            "schwamm",
+            "sponge",
+            "batterie",
+            "battery",
+            "batteries",
+            "alufolie",
+            "foil",
+            "reiniger",
+            "cleaner",
+        ],
+    ),
+    (
+        "drugstore",
+        &[
+            "zahnpasta",
+            "toothpaste",
+            "zahnbürste",
+            "toothbrush",
+            "shampoo",
+            "duschgel",
+            "seife",
+            "soap",
+            "deo",
+            "deodorant",
+            "creme",
+            "lotion",
+            "windeln",
+            "diapers",
+            "taschentücher",
+            "tissues",
+            "pflaster",
+            "plaster",
+            "rasierer",
+            "razor",
+        ],
+    ),
+];
+
+// what the bundled categories are called, in english it is their id
+const NAMES: &[(&str, &[(&str, &str)])] = &[
+    ("produce", &[("de", "Obst & Gemüse")]),
+    ("bakery", &[("de", "Backwaren")]),
+    ("meat", &[("de", "Fleisch & Fisch")]),
+    ("dairy", &[("de", "Milchprodukte")]),
+    ("frozen", &[("de", "Tiefkühl")]),
+    ("pantry", &[("de", "Vorrat")]),
+    ("snacks", &[("de", "Süßwaren")]),
+    ("drinks", &[("de", "Getränke")]),
+    ("household", &[("de", "Haushalt")]),
+    ("drugstore", &[("de", "Drogerie")]),
+];
+
+impl GroceryConfig {
+    /// Finds the category of an item. User categories win over the bundled
+    /// ones, within those the most specific (longest) keyword wins.
+    pub fn classify(&self, content: &str) -> Option<String> {
+        let words: Vec<String> = content
+            .split(|c: char| !c.is_alphanumeric() && c != '-')
+            .filter(|w| !w.is_empty())
+            .map(str::to_lowercase)
+            .collect();
+
+        let custom = self.categories.iter().map(|c| {
+            (
+                c.name.as_str(),
+                c.keywords.iter().map(String::as_str).collect(),
+            )
+        });
+        let bundled = BUNDLED
+            .iter()
+            .filter(|_| self.bundled)
+            .map(|(name, keywords)| (*name, keywords.to_vec()));
+
+        best_match(&words, custom)
+            .or_else(|| best_match(&words, bundled))
+            .map(str::to_string)
+    }
+
+    /// The section or label name of a category in `language`,
+    /// user categories are called as they are configured.
+    pub fn display_name(&self, category: &str) -> String {
+        if self.categories.iter().any(|c| c.name == category) {
+            return category.to_string();
+        }
+        NAMES
+            .iter()
+            .find(|(id, _)| *id == category)
+            .and_then(|(_, names)| {
+                names
+                    .iter()
+                    .find(|(language, _)| language.eq_ignore_ascii_case(&self.language))
+            })
+            .map_or_else(|| category.to_string(), |(_, name)| name.to_string())
+    }
+
+    fn aisle_index(&self, category: Option<&str>) -> usize {
+        category
+            .and_then(|c| {
+                self.aisle_order
+                    .iter()
+                    .position(|a| a.eq_ignore_ascii_case(c))
+            })
+            .unwrap_or(self.aisle_order.len())
+    }
+}
+
+fn best_match<'a, I>(words: &[String], categories: I) -> Option<&'a str>
+where
+    I: Iterator<Item = (&'a str, Vec<&'a str>)>,
+{
+    let mut best: Option<(&str, usize)> = None;
+    for (name, keywords) in categories {
+        for keyword in keywords {
+            let keyword = keyword.to_lowercase();
+            let longer = best.is_none_or(|(_, len)| keyword.len() > len);
+            if longer && words.iter().any(|w| matches_keyword(w, &keyword)) {
+                best = Some((name, keyword.len()));
+            }
+        }
+    }
+    best.map(|(name, _)| name)
+}
+
+// German puts the important part of a compound last ("Vollkornbrot"),
+// English plurals and compounds start with it ("apples", "toothbrush")
+fn matches_keyword(word: &str, keyword: &str) -> bool {
+    let len = keyword.chars().count();
+    word == keyword
+        || (len >= 3 && word.ends_with(keyword))
+        || (len >= 4 && word.starts_with(keyword))
+}
+
+/// Classifies every item, maps the category onto a section or label and
+/// orders the items along the aisles. Subtasks move with their parent,
+/// items stay under the heading they were written under on paper.
+pub fn apply(items: &mut Vec<ListItem>, config: &GroceryConfig, shopping_list: bool) {
+    match config.mode {
+        GroceryMode::Off => return,
+        GroceryMode::ShoppingList if !shopping_list => return,
+        _ => {}
+    }
+
+    let headings: Vec<Option<String>> = items.iter().map(|i| i.section.clone()).collect();
+    for item in items.iter_mut() {
+        item.category = config.classify(&item.content);
+        let Some(category) = item.category.as_deref().map(|c| config.display_name(c)) else {
+            continue;
+        };
+        match config.target {
+            // a heading on paper beats the category
+            CategoryTarget::Section => {
+                if item.section.is_none() {
+                    item.section = Some(category);
+                }
+            }
+            CategoryTarget::Label => {
+                if !item
+                    .labels
+                    .iter()
+                    .any(|l| l.eq_ignore_ascii_case(&category))
+                {
+                    item.labels.push(category);
+                }
+            }
+        }
+    }
+
+    // children directly follow their parent, so a top-level item and
+    // everything up to the next top-level item form one block
+    let mut blocks: Vec<std::ops::Range<usize>> = Vec::new();
+    for (index, item) in items.iter().enumerate() {
+        match blocks.last_mut() {
+            Some(block) if item.parent.is_some() => block.end = index + 1,
+            _ => blocks.push(index..index + 1),
+        }
+    }
+    // the order of the headings is the one on paper, only within them
+    // (or between items without one) it is the one of the aisles
+    for run in blocks.chunk_by_mut(|a, b| headings[a.start] == headings[b.start]) {
+        run.sort_by_key(|block| config.aisle_index(items[block.start].category.as_deref()));
+    }
+
+    let order: Vec<usize> = blocks.into_iter().flatten().collect();
+    let mut new_index = vec![0; items.len()];
+    for (new, &old) in order.iter().enumerate() {
+        new_index[old] = new;
+    }
+    let mut old_items: Vec<Option<ListItem>> = items.drain(..).map(Some).collect();
+    for old in order {
+        let mut item = old_items[old].take().expect("every item is moved once");
+        item.parent = item.parent.map(|p| new_index[p]);
+        items.push(item);
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    extern crate wasm_bindgen_test;
+
+    use wasm_bindgen_test::*;
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    use super::{apply, CategoryTarget, GroceryCategory, GroceryConfig, GroceryMode};
+    use crate::parser::ListItem;
+
+    fn items(contents: &[&str]) -> Vec<ListItem> {
+        contents
+            .iter()
+            .map(|c| ListItem {
+                content: c.to_string(),
+                ..ListItem::default()
+            })
+            .collect()
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn items_are_classified_in_german_and_english() {
+        let config = GroceryConfig::default();
+        let classify = |c: &str| config.classify(c);
+        assert_eq!(Some("dairy".to_string()), classify("2x Milch"));
+        assert_eq!(Some("dairy".to_string()), classify("Hafermilch"));
+        assert_eq!(Some("bakery".to_string()), classify("Vollkornbrot"));
+        assert_eq!(Some("produce".to_string()), classify("apples"));
+        assert_eq!(Some("pantry".to_string()), classify("Reis"));
+        assert_eq!(Some("drugstore".to_string()), classify("Zahnpasta"));
+        assert_eq!(None, classify("Geschenk für Oma"));
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn user_categories_override_the_bundled_table() {
+        let config = GroceryConfig {
+            categories: vec![GroceryCategory {
+                name: "Bio-Laden".to_string(),
+                keywords: vec!["Milch".to_string()],
+            }],
+            ..GroceryConfig::default()
+        };
+        assert_eq!(Some("Bio-Laden".to_string()), config.classify("Milch"));
+        assert_eq!(Some("bakery".to_string()), config.classify("Brot"));
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn items_are_ordered_along_the_aisles() {
+        let mut list = items(&["Seife", "Milch", "Party", "Chips", "Äpfel", "Geschenk"]);
+        list[3].parent = Some(2);
+        let config = GroceryConfig {
+            aisle_order: vec!["produce".to_string(), "dairy".to_string()],
+            ..GroceryConfig::default()
+        };
+        apply(&mut list, &config, true);
+
+        let contents: Vec<_> = list.iter().map(|i| i.content.as_str()).collect();
+        assert_eq!(
+            vec!["Äpfel", "Milch", "Seife", "Party", "Chips", "Geschenk"],
+            contents
+        );
+        assert_eq!(Some(3), list[4].parent);
+        assert_eq!(Some("Obst & Gemüse".to_string()), list[0].section);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn items_stay_under_their_heading() {
+        let mut list = items(&["Seife", "Milch", "Brot", "Äpfel"]);
+        list[0].section = Some("Drogerie dm".to_string());
+        list[1].section = Some("Drogerie dm".to_string());
+        list[2].section = Some("Markt".to_string());
+        list[3].section = Some("Markt".to_string());
+        apply(&mut list, &GroceryConfig::default(), true);
+
+        let contents: Vec<_> = list.iter().map(|i| i.content.as_str()).collect();
+        assert_eq!(vec!["Milch", "Seife", "Äpfel", "Brot"], contents);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn categories_can_become_labels() {
+        let mut list = items(&["Milch"]);
+        let config = GroceryConfig {
+            target: CategoryTarget::Label,
+            ..GroceryConfig::default()
+        };
+        apply(&mut list, &config, true);
+        assert_eq!(vec!["Milchprodukte".to_string()], list[0].labels);
+        assert_eq!(None, list[0].section);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn category_names_follow_the_language() {
+        let mut list = items(&["Milch"]);
+        let config = GroceryConfig {
+            language: "en".to_string(),
+            ..GroceryConfig::default()
+        };
+        apply(&mut list, &config, true);
+        assert_eq!(Some("dairy".to_string()), list[0].section);
+        assert_eq!(Some("dairy".to_string()), list[0].category);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn only_the_shopping_list_is_categorised_by_default() {
+        let mut list = items(&["Milch"]);
+        apply(&mut list, &GroceryConfig::default(), false);
+        assert_eq!(None, list[0].category);
+
+        let config = GroceryConfig {
+            mode: GroceryMode::Always,
+            ..GroceryConfig::default()
+        };
+        apply(&mut list, &config, false);
+        assert_eq!(Some("dairy".to_string()), list[0].category);
+    }
+}
@@ -0,0 +1,401 @@
+use crate::secret::Secret;
+use crate::utils::error_string;
+use async_trait::async_trait;
+use js_sys::{Promise, Uint8Array};
+use std::fmt;
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{Request, RequestInit, Response};
+use zeroize::Zeroize;
+
+// headers that carry credentials, Debug prints *** instead of their value
+const SECRET_HEADERS: &[&str] = &[
+    "authorization",
+    "ocp-apim-subscription-key",
+    "x-amz-security-token",
+];
+
+/// A plain http request, so that providers can be tested without a browser.
+/// Header values and body are wiped when it is dropped, like a `Secret`.
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct HttpRequest {
+    pub method: String,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl HttpRequest {
+    pub fn new(method: &str, url: &str) -> Self {
+        HttpRequest {
+            method: method.to_string(),
+            url: url.to_string(),
+            headers: Vec::new(),
+            body: Vec::new(),
+        }
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// A header with a credential as value, it is moved in without a copy.
+    pub fn secret_header(mut self, name: &str, value: Secret) -> Self {
+        self.headers.push((name.to_string(), value.into_string()));
+        self
+    }
+
+    pub fn bearer(self, token: &Secret) -> Self {
+        self.secret_header(
+            "Authorization",
+            Secret::new(format!("Bearer {}", token.expose())),
+        )
+    }
+
+    pub fn body(mut self, body: Vec<u8>) -> Self {
+        self.body = body;
+        self
+    }
+}
+
+// the headers carry tokens and keys, the body forms with client secrets
+// and assertions; copies the browser or ureq make are out of reach
+impl Drop for HttpRequest {
+    fn drop(&mut self) {
+        for (_, value) in self.headers.iter_mut() {
+            value.zeroize();
+        }
+        self.body.zeroize();
+    }
+}
+
+impl fmt::Debug for HttpRequest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let headers: Vec<(&str, &str)> = self
+            .headers
+            .iter()
+            .map(|(name, value)| {
+                let secret = SECRET_HEADERS.iter().any(|s| name.eq_ignore_ascii_case(s));
+                (name.as_str(), if secret { "***" } else { value.as_str() })
+            })
+            .collect();
+        f.debug_struct("HttpRequest")
+            .field("method", &self.method)
+            .field("url", &self.url)
+            .field("headers", &headers)
+            // forms can hold secrets as well (client secrets, assertions)
+            .field("body", &format_args!("{} bytes", self.body.len()))
+            .finish()
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl HttpResponse {
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    /// Header names are case-insensitive.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn json<T>(&self) -> Result<T, String>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        serde_json::from_slice(&self.body).map_err(|e| e.to_string())
+    }
+
+    /// Like `json`, but an error status is an error with the body as message.
+    pub fn into_json<T>(self) -> Result<T, String>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        if !self.is_success() {
+            return Err(format!(
+                "status {}: {}",
+                self.status,
+                String::from_utf8_lossy(&self.body)
+            ));
+        }
+        self.json()
+    }
+}
+
+/// Sends the requests of all providers and sinks, the browser's fetch in
+/// wasm and a blocking client natively, so that they can be tested with a mock.
+#[async_trait(?Send)]
+pub trait HttpClient {
+    async fn send(&self, request: HttpRequest) -> Result<HttpResponse, String>;
+
+    /// Waits between two polls of a long running operation.
+    async fn wait(&self, millis: u32);
+}
+
+/// The browser's fetch.
+#[derive(Default)]
+pub struct FetchClient;
+
+#[async_trait(?Send)]
+impl HttpClient for FetchClient {
+    async fn send(&self, request: HttpRequest) -> Result<HttpResponse, String> {
+        let opts = RequestInit::new();
+        opts.set_method(&request.method);
+        if !request.body.is_empty() {
+            opts.set_body(&Uint8Array::from(&request.body[..]));
+        }
+        let js_request =
+            Request::new_with_str_and_init(&request.url, &opts).map_err(|e| error_string(&e))?;
+        for (name, value) in &request.headers {
+            js_request
+                .headers()
+                .set(name, value)
+                .map_err(|e| error_string(&e))?;
+        }
+
+        let window = web_sys::window().ok_or("no window")?;
+        let response: Response = JsFuture::from(window.fetch_with_request(&js_request))
+            .await
+            .map_err(|e| error_string(&e))?
+            .dyn_into()
+            .map_err(|e| error_string(&e))?;
+
+        let mut headers = Vec::new();
+        if let Ok(Some(entries)) = js_sys::try_iter(response.headers().as_ref()) {
+            for entry in entries.flatten() {
+                let pair: js_sys::Array = entry.unchecked_into();
+                if let (Some(name), Some(value)) =
+                    (pair.get(0).as_string(), pair.get(1).as_string())
+                {
+                    headers.push((name, value));
+                }
+            }
+        }
+        let buffer = JsFuture::from(response.array_buffer().map_err(|e| error_string(&e))?)
+            .await
+            .map_err(|e| error_string(&e))?;
+
+        Ok(HttpResponse {
+            status: response.status(),
+            headers,
+            body: Uint8Array::new(&buffer).to_vec(),
+        })
+    }
+
+    async fn wait(&self, millis: u32) {
+        let timeout = Promise::new(&mut |resolve, _| {
+            if let Some(window) = web_sys::window() {
+                let _ = window
+                    .set_timeout_with_callback_and_timeout_and_arguments_0(&resolve, millis as i32);
+            }
+        });
+        let _: Result<JsValue, JsValue> = JsFuture::from(timeout).await;
+    }
+}
+
+/// Blocking requests for native builds, the futures resolve right away.
+#[cfg(not(target_arch = "wasm32"))]
+pub struct NativeClient {
+    agent: ureq::Agent,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Default for NativeClient {
+    fn default() -> Self {
+        let config = ureq::Agent::config_builder()
+            // error statuses are answers as well, the providers read their body
+            .http_status_as_error(false)
+            // webdav (caldav) has methods like PROPFIND and REPORT
+            .allow_non_standard_methods(true)
+            .build();
+        NativeClient {
+            agent: config.into(),
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[async_trait(?Send)]
+impl HttpClient for NativeClient {
+    async fn send(&self, mut request: HttpRequest) -> Result<HttpResponse, String> {
+        let mut builder = ureq::http::Request::builder()
+            .method(request.method.as_str())
+            .uri(&request.url);
+        for (name, value) in &request.headers {
+            builder = builder.header(name, value);
+        }
+        let body = std::mem::take(&mut request.body);
+        let native_request = builder.body(body).map_err(|e| e.to_string())?;
+        let mut response = self.agent.run(native_request).map_err(|e| e.to_string())?;
+
+        let headers = response
+            .headers()
+            .iter()
+            .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+            .collect();
+        Ok(HttpResponse {
+            status: response.status().as_u16(),
+            headers,
+            body: response
+                .body_mut()
+                .read_to_vec()
+                .map_err(|e| e.to_string())?,
+        })
+    }
+
+    async fn wait(&self, millis: u32) {
+        std::thread::sleep(std::time::Duration::from_millis(u64::from(millis)));
+    }
+}
+
+/// The client the providers use when none is passed in.
+#[cfg(target_arch = "wasm32")]
+pub type DefaultClient = FetchClient;
+#[cfg(not(target_arch = "wasm32"))]
+pub type DefaultClient = NativeClient;
+
+/// Answers with recorded responses and remembers what was sent.
+#[cfg(test)]
+pub(crate) struct Replay {
+    pub responses: std::cell::RefCell<std::collections::VecDeque<HttpResponse>>,
+    pub requests: std::cell::RefCell<Vec<HttpRequest>>,
+}
+
+#[cfg(test)]
+impl Replay {
+    pub fn new(responses: Vec<HttpResponse>) -> Self {
+        Replay {
+            responses: std::cell::RefCell::new(responses.into()),
+            requests: std::cell::RefCell::new(Vec::new()),
+        }
+    }
+}
+
+#[cfg(test)]
+#[async_trait(?Send)]
+impl HttpClient for Replay {
+    async fn send(&self, request: HttpRequest) -> Result<HttpResponse, String> {
+        self.requests.borrow_mut().push(request);
+        self.responses
+            .borrow_mut()
+            .pop_front()
+            .ok_or_else(|| "no recorded response left".to_string())
+    }
+
+    async fn wait(&self, _millis: u32) {}
+}
+
+/// A local http server for the native tests of the providers and sinks.
+/// It answers every connection with the next canned response and
+/// hands out the requests it got (the url is only the path).
+#[cfg(all(test, not(target_arch = "wasm32")))]
+pub(crate) struct MockServer {
+    pub url: String,
+    handle: std::thread::JoinHandle<Vec<HttpRequest>>,
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+impl MockServer {
+    pub fn start(responses: Vec<HttpResponse>) -> Self {
+        MockServer::start_with(|_| responses)
+    }
+
+    /// For responses that have to point back to the server (e.g. next links).
+    pub fn start_with(responses: impl FnOnce(&str) -> Vec<HttpResponse>) -> Self {
+        use std::io::{BufRead, BufReader, Read, Write};
+        use std::time::{Duration, Instant};
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").expect("mock server must bind");
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        listener.set_nonblocking(true).unwrap();
+        let responses = responses(&url);
+
+        let handle = std::thread::spawn(move || {
+            let deadline = Instant::now() + Duration::from_secs(5);
+            let mut requests = Vec::new();
+            for response in responses {
+                let stream = loop {
+                    match listener.accept() {
+                        Ok((stream, _)) => break stream,
+                        Err(_) if Instant::now() < deadline => {
+                            std::thread::sleep(Duration::from_millis(5))
+                        }
+                        Err(_) => return requests,
+                    }
+                };
+                stream.set_nonblocking(false).unwrap();
+                let mut reader = BufReader::new(stream);
+
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                let mut parts = request_line.split_whitespace();
+                let mut request = HttpRequest::new(
+                    parts.next().unwrap_or_default(),
+                    parts.next().unwrap_or_default(),
+                );
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    let line = line.trim_end();
+                    if line.is_empty() {
+                        break;
+                    }
+                    if let Some((name, value)) = line.split_once(':') {
+                        request = request.header(&name.to_lowercase(), value.trim());
+                    }
+                }
+                let length = request
+                    .headers
+                    .iter()
+                    .find(|(name, _)| name == "content-length")
+                    .and_then(|(_, value)| value.parse().ok())
+                    .unwrap_or(0);
+                let mut body = vec![0; length];
+                reader.read_exact(&mut body).unwrap();
+                requests.push(request.body(body));
+
+                let mut stream = reader.into_inner();
+                let mut head = format!("HTTP/1.1 {} Mock\r\n", response.status);
+                for (name, value) in &response.headers {
+                    head.push_str(&format!("{}: {}\r\n", name, value));
+                }
+                head.push_str(&format!(
+                    "Content-Length: {}\r\nConnection: close\r\n\r\n",
+                    response.body.len()
+                ));
+                // the client may have given up already
+                let _ = stream.write_all(head.as_bytes());
+                let _ = stream.write_all(&response.body);
+            }
+            requests
+        });
+        MockServer { url, handle }
+    }
+
+    /// Waits until every response was sent.
+    pub fn requests(self) -> Vec<HttpRequest> {
+        self.handle.join().expect("mock server must not panic")
+    }
+}
+
+#[cfg(test)]
+impl HttpResponse {
+    pub(crate) fn with_json(status: u16, json: &str) -> Self {
+        HttpResponse {
+            status,
+            headers: vec![("Content-Type".to_string(), "application/json".to_string())],
+            body: json.as_bytes().to_vec(),
+        }
+    }
+}
@@ -0,0 +1,304 @@
+use crate::checkbox::CheckboxState;
+use crate::parser::ListItem;
+use chrono::{DateTime, NaiveDate, Utc};
+use uuid::Uuid;
+
+const PRODID: &str = "-//pen-to-todoist//EN";
+// content lines are folded after this many octets (rfc 5545, 3.1)
+const MAX_LINE_OCTETS: usize = 75;
+
+/// An iCalendar VTODO, as it is stored in a caldav calendar.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Vtodo {
+    pub uid: String,
+    pub stamp: DateTime<Utc>,
+    pub summary: String,
+    pub description: Option<String>,
+    // todoist scale, 4 is urgent
+    pub priority: Option<u8>,
+    pub categories: Vec<String>,
+    pub due: Option<NaiveDate>,
+    pub completed: bool,
+    pub parent_uid: Option<String>,
+}
+
+/// How the UIDs of exported todos are made.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Uids {
+    // a new uuid (v4) for every todo
+    Random,
+    // a uuid (v5) of the position and content of the item in the namespace
+    // of the list, the same list gives the same file, so that calendars
+    // update instead of duplicating (and other lists do not collide)
+    Stable(Uuid),
+}
+
+impl Uids {
+    /// Stable UIDs in a namespace made from the name (or id) of the list.
+    pub fn for_list(list: &str) -> Self {
+        Uids::Stable(Uuid::new_v5(&Uuid::NAMESPACE_OID, list.as_bytes()))
+    }
+}
+
+impl Vtodo {
+    pub fn from_item(item: &ListItem, uid: &str, stamp: DateTime<Utc>) -> Self {
+        Vtodo {
+            uid: uid.to_string(),
+            stamp,
+            summary: item.content.clone(),
+            description: item.description.clone(),
+            priority: item.priority,
+            categories: item.labels.clone(),
+            due: item
+                .due
+                .as_deref()
+                .and_then(|due| NaiveDate::parse_from_str(due, "%Y-%m-%d").ok()),
+            completed: false,
+            parent_uid: None,
+        }
+    }
+
+    fn lines(&self) -> Vec<String> {
+        let mut lines = vec![
+            "BEGIN:VTODO".to_string(),
+            format!("UID:{}", self.uid),
+            format!("DTSTAMP:{}", self.stamp.format("%Y%m%dT%H%M%SZ")),
+            format!("SUMMARY:{}", escape_text(&self.summary)),
+        ];
+        if let Some(description) = &self.description {
+            lines.push(format!("DESCRIPTION:{}", escape_text(description)));
+        }
+        if let Some(due) = self.due {
+            lines.push(format!("DUE;VALUE=DATE:{}", due.format("%Y%m%d")));
+        }
+        if let Some(priority) = self.priority.and_then(ical_priority) {
+            lines.push(format!("PRIORITY:{}", priority));
+        }
+        if !self.categories.is_empty() {
+            let categories: Vec<String> = self.categories.iter().map(|c| escape_text(c)).collect();
+            lines.push(format!("CATEGORIES:{}", categories.join(",")));
+        }
+        let status = if self.completed {
+            "COMPLETED"
+        } else {
+            "NEEDS-ACTION"
+        };
+        lines.push(format!("STATUS:{}", status));
+        if let Some(parent) = &self.parent_uid {
+            lines.push(format!("RELATED-TO;RELTYPE=PARENT:{}", parent));
+        }
+        lines.push("END:VTODO".to_string());
+        lines
+    }
+}
+
+/// A VCALENDAR holding the todos, with folded CRLF-terminated lines.
+pub fn calendar(todos: &[Vtodo]) -> String {
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        format!("PRODID:{}", PRODID),
+    ];
+    lines.extend(todos.iter().flat_map(Vtodo::lines));
+    lines.push("END:VCALENDAR".to_string());
+    lines.iter().map(|line| fold(line) + "\r\n").collect()
+}
+
+/// An `.ics` file with a VTODO for every item, subtasks point to their
+/// parent with RELATED-TO and ticked items are COMPLETED.
+pub fn export_items(items: &[ListItem], uids: Uids, stamp: DateTime<Utc>) -> String {
+    let uids: Vec<String> = items
+        .iter()
+        .enumerate()
+        .map(|(index, item)| match uids {
+            Uids::Random => Uuid::new_v4().to_string(),
+            Uids::Stable(namespace) => {
+                let name = format!("{}\n{}", index, item.content);
+                Uuid::new_v5(&namespace, name.as_bytes()).to_string()
+            }
+        })
+        .collect();
+    let todos: Vec<Vtodo> = items
+        .iter()
+        .zip(&uids)
+        .map(|(item, uid)| Vtodo {
+            completed: item.checkbox == Some(CheckboxState::Checked),
+            parent_uid: item.parent.and_then(|p| uids.get(p).cloned()),
+            ..Vtodo::from_item(item, uid, stamp)
+        })
+        .collect();
+    calendar(&todos)
+}
+
+// todoist 4 (urgent) to 1 (normal), icalendar 1 (highest) to 9 (lowest),
+// normal tasks get none
+fn ical_priority(priority: u8) -> Option<u8> {
+    match priority {
+        4 => Some(1),
+        3 => Some(3),
+        2 => Some(5),
+        _ => None,
+    }
+}
+
+/// Escapes a TEXT value (rfc 5545, 3.3.11).
+pub fn escape_text(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            ';' => escaped.push_str("\\;"),
+            ',' => escaped.push_str("\\,"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Reverses `escape_text`.
+pub fn unescape_text(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') | Some('N') => unescaped.push('\n'),
+            Some(other) => unescaped.push(other),
+            None => {}
+        }
+    }
+    unescaped
+}
+
+/// Breaks a content line into lines of at most 75 octets,
+/// continuation lines start with a space. Never splits a character.
+pub fn fold(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len() + line.len() / MAX_LINE_OCTETS * 3);
+    let mut octets = 0;
+    for c in line.chars() {
+        if octets + c.len_utf8() > MAX_LINE_OCTETS {
+            folded.push_str("\r\n ");
+            // the space counts as well
+            octets = 1;
+        }
+        folded.push(c);
+        octets += c.len_utf8();
+    }
+    folded
+}
+
+/// Joins folded lines again and splits the text into content lines.
+pub fn unfold(text: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in text.split('\n') {
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        match (
+            line.strip_prefix(' ').or_else(|| line.strip_prefix('\t')),
+            lines.last_mut(),
+        ) {
+            (Some(continued), Some(last)) => last.push_str(continued),
+            _ if line.is_empty() => {}
+            _ => lines.push(line.to_string()),
+        }
+    }
+    lines
+}
+
+#[cfg(test)]
+mod test {
+
+    extern crate wasm_bindgen_test;
+
+    use wasm_bindgen_test::*;
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    use super::{escape_text, export_items, fold, unescape_text, unfold, Uids};
+    use crate::checkbox::CheckboxState;
+    use crate::parser::ListItem;
+    use chrono::{TimeZone, Utc};
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn text_is_escaped_and_unescaped() {
+        let text = "Brot, Butter; Käse\\Wurst\nBier";
+        let escaped = escape_text(text);
+        assert_eq!("Brot\\, Butter\\; Käse\\\\Wurst\\nBier", escaped);
+        assert_eq!(text, unescape_text(&escaped));
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn long_lines_are_folded_without_splitting_chars() {
+        let line = format!("SUMMARY:{}", "ä".repeat(40));
+        let folded = fold(&line);
+        for part in folded.split("\r\n") {
+            assert!(part.len() <= 75);
+        }
+        assert_eq!(vec![line], unfold(&folded));
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn items_export_to_a_stable_ics_file() {
+        let items = vec![
+            ListItem {
+                content: "Geburtstag vorbereiten".to_string(),
+                priority: Some(4),
+                due: Some("2024-06-01".to_string()),
+                labels: vec!["familie".to_string(), "fest".to_string()],
+                ..ListItem::default()
+            },
+            ListItem {
+                content: "Kuchen: Mehl, Zucker; Eier".to_string(),
+                parent: Some(0),
+                checkbox: Some(CheckboxState::Checked),
+                ..ListItem::default()
+            },
+            ListItem {
+                content:
+                    "Einladungen an alle Nachbarn im Haus verteilen und die Getränke bestellen"
+                        .to_string(),
+                description: Some("Liste am Kühlschrank\nnicht vergessen".to_string()),
+                priority: Some(2),
+                ..ListItem::default()
+            },
+        ];
+        let stamp = Utc.with_ymd_and_hms(2024, 5, 20, 8, 30, 0).unwrap();
+
+        let ics = export_items(&items, Uids::for_list("Geburtstag"), stamp);
+
+        assert_eq!(include_str!("../test-assets/export.ics"), ics);
+        assert_eq!(
+            ics,
+            export_items(&items, Uids::for_list("Geburtstag"), stamp)
+        );
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn stable_uids_depend_on_the_list() {
+        let items = vec![ListItem {
+            content: "Milch".to_string(),
+            ..ListItem::default()
+        }];
+        let stamp = Utc.with_ymd_and_hms(2024, 5, 20, 8, 30, 0).unwrap();
+        assert_ne!(
+            export_items(&items, Uids::for_list("Einkaufsliste"), stamp),
+            export_items(&items, Uids::for_list("Wochenmarkt"), stamp)
+        );
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn random_uids_differ() {
+        let items = vec![ListItem {
+            content: "Milch".to_string(),
+            ..ListItem::default()
+        }];
+        let stamp = Utc.with_ymd_and_hms(2024, 5, 20, 8, 30, 0).unwrap();
+        assert_ne!(
+            export_items(&items, Uids::Random, stamp),
+            export_items(&items, Uids::Random, stamp)
+        );
+    }
+}
@@ -0,0 +1,343 @@
+use crate::parser::ListItem;
+use serde::{Deserialize, Serialize};
+
+/// A line of recognized text together with where it was found on the image.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct TextLine {
+    pub text: String,
+    // leftmost x-coordinate of all words in the line
+    pub left: u32,
+    pub top: u32,
+    pub height: u32,
+    pub words: Vec<WordBox>,
+}
+
+impl TextLine {
+    pub fn new(text: &str) -> Self {
+        TextLine {
+            text: text.to_string(),
+            ..TextLine::default()
+        }
+    }
+
+    /// The line spans the boxes of all its words.
+    pub fn from_words(text: &str, words: Vec<WordBox>) -> Self {
+        if words.is_empty() {
+            return TextLine::new(text);
+        }
+        let left = words.iter().map(|w| w.left).min().unwrap_or(0);
+        let top = words.iter().map(|w| w.top).min().unwrap_or(0);
+        let bottom = words.iter().map(|w| w.top + w.height).max().unwrap_or(0);
+        TextLine {
+            text: text.to_string(),
+            left,
+            top,
+            height: bottom.saturating_sub(top),
+            words,
+        }
+    }
+
+    /// The mean confidence of the words that have one.
+    pub fn confidence(&self) -> Option<f32> {
+        let known: Vec<f32> = self.words.iter().filter_map(|w| w.confidence).collect();
+        (!known.is_empty()).then(|| known.iter().sum::<f32>() / known.len() as f32)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct WordBox {
+    pub text: String,
+    pub left: u32,
+    pub top: u32,
+    pub width: u32,
+    pub height: u32,
+    // 0.0 to 1.0, if the provider reports one
+    pub confidence: Option<f32>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct LayoutConfig {
+    // turn indented lines into subtasks
+    pub nesting: bool,
+    // how far (relative to the usual line height) a line has to be
+    // shifted to the right to count as indented
+    pub indent_ratio: f32,
+    pub headings: HeadingConfig,
+}
+
+impl Default for LayoutConfig {
+    fn default() -> Self {
+        LayoutConfig {
+            nesting: true,
+            indent_ratio: 0.6,
+            headings: HeadingConfig::default(),
+        }
+    }
+}
+
+/// Decides which lines are headings that group the lines below them
+/// into a todoist section.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct HeadingConfig {
+    pub enabled: bool,
+    // a line this much taller than the usual line is a heading
+    pub height_ratio: f32,
+    // "Obst:" is a heading, unless the lines below are indented
+    // (then it is a parent task, see `nest_items`)
+    pub trailing_colon: bool,
+    // a line followed by "----" or "____" is a heading
+    pub underline: bool,
+}
+
+impl Default for HeadingConfig {
+    fn default() -> Self {
+        HeadingConfig {
+            enabled: true,
+            height_ratio: 1.4,
+            trailing_colon: true,
+            underline: true,
+        }
+    }
+}
+
+/// Removes heading lines (and the underlines below them) and returns the
+/// remaining lines, each with the name of the heading it belongs to.
+pub fn split_headings(
+    lines: Vec<TextLine>,
+    config: &LayoutConfig,
+) -> Vec<(TextLine, Option<String>)> {
+    let headings = &config.headings;
+    if !headings.enabled {
+        return lines.into_iter().map(|l| (l, None)).collect();
+    }
+    let median = median_height(&lines) as f32;
+    let tolerance = median * config.indent_ratio;
+
+    let mut sectioned = Vec::with_capacity(lines.len());
+    let mut section = None;
+    let mut lines = lines.into_iter().peekable();
+    while let Some(line) = lines.next() {
+        if headings.underline && is_underline(&line.text) {
+            continue;
+        }
+        let next = lines.peek();
+        let underlined = headings.underline && next.is_some_and(|n| is_underline(&n.text));
+        // without measurements (e.g. a single item) there is no "taller"
+        let taller = median > 0.0 && line.height as f32 >= median * headings.height_ratio;
+        let colon = headings.trailing_colon
+            && line.text.trim_end().ends_with(':')
+            && next.is_none_or(|n| n.left as f32 <= line.left as f32 + tolerance);
+
+        if underlined || taller || colon {
+            let name = line.text.trim().trim_end_matches(':').trim_end();
+            if !name.is_empty() {
+                section = Some(name.to_string());
+                continue;
+            }
+        }
+        sectioned.push((line, section.clone()));
+    }
+    sectioned
+}
+
+fn is_underline(text: &str) -> bool {
+    let text = text.trim();
+    text.chars().count() >= 2 && text.chars().all(|c| "_-—–=~".contains(c))
+}
+
+/// Infers the indentation hierarchy of the lines and sets `parent` on
+/// the items accordingly. `items` and `lines` have to match one to one.
+///
+/// A line is a child of the closest line above it that starts further left
+/// by more than the tolerance. Lines within the tolerance are siblings.
+pub fn nest_items(lines: &[TextLine], items: &mut [ListItem], config: &LayoutConfig) {
+    if !config.nesting || lines.len() != items.len() {
+        return;
+    }
+    let tolerance = median_height(lines) as f32 * config.indent_ratio;
+
+    // (left, index) of the lines that can still get children
+    let mut open: Vec<(u32, usize)> = Vec::new();
+    for (index, line) in lines.iter().enumerate() {
+        // subtasks never reach into another section
+        if index > 0 && items[index].section != items[index - 1].section {
+            open.clear();
+        }
+        while let Some(&(left, _)) = open.last() {
+            if line.left as f32 > left as f32 + tolerance {
+                break;
+            }
+            open.pop();
+        }
+        items[index].parent = open.last().map(|&(_, parent)| parent);
+        open.push((line.left, index));
+    }
+
+    // "Party:" reads as a heading on paper, the colon is noise in todoist
+    for parent in items.iter().filter_map(|i| i.parent).collect::<Vec<_>>() {
+        let content = &mut items[parent].content;
+        if content.ends_with(':') {
+            content.pop();
+            let trimmed_len = content.trim_end().len();
+            content.truncate(trimmed_len);
+        }
+    }
+}
+
+fn median_height(lines: &[TextLine]) -> u32 {
+    let mut heights: Vec<u32> = lines.iter().map(|l| l.height).collect();
+    heights.sort_unstable();
+    heights.get(heights.len() / 2).copied().unwrap_or(0)
+}
+
+#[cfg(test)]
+mod test {
+
+    extern crate wasm_bindgen_test;
+
+    use wasm_bindgen_test::*;
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    use super::{nest_items, split_headings, LayoutConfig, TextLine};
+    use crate::parser::ListItem;
+
+    fn line(text: &str, left: u32) -> TextLine {
+        TextLine {
+            left,
+            height: 30,
+            ..TextLine::new(text)
+        }
+    }
+
+    fn items_for(lines: &[TextLine]) -> Vec<ListItem> {
+        lines
+            .iter()
+            .map(|l| ListItem {
+                content: l.text.clone(),
+                ..ListItem::default()
+            })
+            .collect()
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn indented_lines_become_children() {
+        let lines = vec![
+            line("Party:", 10),
+            line("Chips", 52),
+            line("Cola", 48),
+            line("Brot", 12),
+        ];
+        let mut items = items_for(&lines);
+        nest_items(&lines, &mut items, &LayoutConfig::default());
+
+        let parents: Vec<_> = items.iter().map(|i| i.parent).collect();
+        assert_eq!(vec![None, Some(0), Some(0), None], parents);
+        assert_eq!("Party", items[0].content);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn deeper_indentation_nests_further() {
+        let lines = vec![
+            line("Urlaub", 5),
+            line("Packen", 45),
+            line("Socken", 90),
+            line("Buchen", 40),
+        ];
+        let mut items = items_for(&lines);
+        nest_items(&lines, &mut items, &LayoutConfig::default());
+
+        let parents: Vec<_> = items.iter().map(|i| i.parent).collect();
+        assert_eq!(vec![None, Some(0), Some(1), Some(0)], parents);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn nesting_can_be_turned_off() {
+        let lines = vec![line("Party:", 10), line("Chips", 52)];
+        let mut items = items_for(&lines);
+        let config = LayoutConfig {
+            nesting: false,
+            ..LayoutConfig::default()
+        };
+        nest_items(&lines, &mut items, &config);
+
+        assert!(items.iter().all(|i| i.parent.is_none()));
+        assert_eq!("Party:", items[0].content);
+    }
+
+    fn tall_line(text: &str, left: u32, height: u32) -> TextLine {
+        TextLine {
+            height,
+            ..line(text, left)
+        }
+    }
+
+    fn sections(sectioned: &[(TextLine, Option<String>)]) -> Vec<(&str, Option<&str>)> {
+        sectioned
+            .iter()
+            .map(|(l, s)| (l.text.as_str(), s.as_deref()))
+            .collect()
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn taller_lines_are_headings() {
+        let lines = vec![
+            tall_line("Obst", 10, 50),
+            line("Äpfel", 10),
+            line("Bananen", 10),
+            tall_line("Drogerie", 10, 48),
+            line("Seife", 10),
+        ];
+        let sectioned = split_headings(lines, &LayoutConfig::default());
+        assert_eq!(
+            vec![
+                ("Äpfel", Some("Obst")),
+                ("Bananen", Some("Obst")),
+                ("Seife", Some("Drogerie"))
+            ],
+            sections(&sectioned)
+        );
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn colon_and_underline_make_headings() {
+        let lines = vec![
+            line("Milch", 10),
+            line("Work:", 10),
+            line("Mails", 10),
+            line("Obst", 10),
+            line("-----", 10),
+            line("Kiwi", 10),
+        ];
+        let sectioned = split_headings(lines, &LayoutConfig::default());
+        assert_eq!(
+            vec![
+                ("Milch", None),
+                ("Mails", Some("Work")),
+                ("Kiwi", Some("Obst"))
+            ],
+            sections(&sectioned)
+        );
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn colon_with_indented_lines_stays_a_parent() {
+        let lines = vec![line("Party:", 10), line("Chips", 52)];
+        let sectioned = split_headings(lines, &LayoutConfig::default());
+        assert_eq!(
+            vec![("Party:", None), ("Chips", None)],
+            sections(&sectioned)
+        );
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn nesting_stops_at_section_boundaries() {
+        let lines = vec![line("Äpfel", 10), line("Chips", 52)];
+        let mut items = items_for(&lines);
+        items[1].section = Some("Party".to_string());
+        nest_items(&lines, &mut items, &LayoutConfig::default());
+
+        assert_eq!(None, items[1].parent);
+    }
+}
@@ -12,14 +12,60 @@
 // - Use retrieved access_token as bearer to make requests to Vision API
 
 pub mod auth;
+pub mod azure_read;
+pub mod caldav;
+pub mod checkbox;
+pub mod credentials;
+pub mod ensemble;
+pub mod export;
+pub mod google_oauth;
+pub mod google_tasks;
+pub mod grocery;
+pub mod http;
+pub mod ical;
 pub mod jwt;
+pub mod layout;
+pub mod ms_todo;
+pub mod normalize;
+pub mod ocr;
+pub mod options;
+pub mod parser;
+pub mod pipeline;
+pub mod secret;
+pub mod shopping;
+pub mod sigv4;
+pub mod sink;
+pub mod spelling;
+#[cfg(all(feature = "tesseract", not(target_arch = "wasm32")))]
+pub mod tesseract;
+pub mod textract;
 pub mod todoist;
+pub mod todoist_oauth;
+pub mod translate;
 pub mod types;
 pub mod utils;
 pub mod vision_api;
 
-use todoist::{fetch_all_projects, make_or_update_project};
-use vision_api::{image_to_list_items, image_to_single_item, TodoItem};
+use azure_read::AzureRead;
+use caldav::CalDav;
+use credentials::validate_service_account;
+use ensemble::Ensemble;
+use export::ExportFormat;
+use google_oauth::{Consent, GoogleUserAuth, UserTokens};
+use google_tasks::GoogleTasks;
+use http::DefaultClient;
+use ms_todo::MicrosoftTodo;
+use ocr::{OcrEngine, OcrProvider};
+use options::{Options, SinkKind};
+use parser::{parse_item, ListItem};
+use pipeline::{lines_to_items, Context};
+use secret::Secret;
+use sink::TaskSink;
+use std::collections::HashMap;
+use textract::Textract;
+use todoist::{fetch_all_projects, fetch_task_contents, make_or_update_project, Todoist};
+use todoist_oauth::TodoistOAuth;
+use vision_api::GoogleVision;
 use wasm_bindgen::prelude::*;
 
 #[wasm_bindgen]
@@ -29,7 +75,43 @@ pub async fn list_from_handwriting(
     todoist_token: String,
     credentials_json: String,
 ) -> JsValue {
-    todoist_from_handwriting(project_id, img_data, todoist_token, credentials_json, false).await
+    todoist_from_handwriting(
+        project_id,
+        img_data,
+        Secret::from(todoist_token),
+        credentials_json,
+        Options::default(),
+        false,
+    )
+    .await
+}
+
+/// Same as `list_from_handwriting`, but the parsing of the items can be
+/// tweaked by passing the `Options` as json.
+#[wasm_bindgen]
+pub async fn list_from_handwriting_with_options(
+    project_id: u32,
+    img_data: String,
+    todoist_token: String,
+    credentials_json: String,
+    options_json: String,
+) -> JsValue {
+    let options = match Options::from_json(&options_json) {
+        Ok(options) => options,
+        Err(e) => {
+            utils::console_log("Invalid options", &e);
+            return JsValue::null();
+        }
+    };
+    todoist_from_handwriting(
+        project_id,
+        img_data,
+        Secret::from(todoist_token),
+        credentials_json,
+        options,
+        false,
+    )
+    .await
 }
 
 #[wasm_bindgen]
@@ -39,31 +121,203 @@ pub async fn largest_item_from_handwriting(
     todoist_token: String,
     credentials_json: String,
 ) -> JsValue {
-    todoist_from_handwriting(project_id, img_data, todoist_token, credentials_json, true).await
+    todoist_from_handwriting(
+        project_id,
+        img_data,
+        Secret::from(todoist_token),
+        credentials_json,
+        Options::default(),
+        true,
+    )
+    .await
 }
 
-async fn todoist_from_handwriting(
+/// Recognizes and parses the items without creating any todoist-task,
+/// so that a client can show what would be created (e.g. which items are
+/// already ticked on paper, or which got a spelling correction).
+/// `project_id` and `todoist_token` are only used to read existing tasks.
+#[wasm_bindgen]
+pub async fn preview_from_handwriting(
     project_id: u32,
     img_data: String,
     todoist_token: String,
     credentials_json: String,
-    single_todo: bool,
+    options_json: String,
 ) -> JsValue {
-    utils::console_log("project_id u32", &project_id);
-    let image_data = if single_todo {
-        image_to_single_item(img_data, &credentials_json).await
-    } else {
-        image_to_list_items(img_data, &credentials_json).await
+    let options = match Options::from_json(&options_json) {
+        Ok(options) => options,
+        Err(e) => {
+            utils::console_log("Invalid options", &e);
+            return JsValue::null();
+        }
     };
+    let todoist_token = Secret::from(todoist_token);
+    let context = pipeline_context(project_id, &todoist_token, &options).await;
+    match items_from_handwriting(img_data, &credentials_json, &options, false, &context).await {
+        Ok(items) => utils::to_js(&items).unwrap_or(JsValue::NULL),
+        Err(e) => {
+            utils::console_log("Error", &e);
+            JsValue::null()
+        }
+    }
+}
 
-    match image_data {
-        Ok(TodoItem::List(list)) => {
-            make_or_update_project(project_id, list.iter().map(|s| s.as_str()), &todoist_token)
+/// Runs the providers of `options.ensemble` and tells per line which
+/// provider's text was taken and what the others read.
+#[wasm_bindgen]
+pub async fn ensemble_report_from_handwriting(
+    img_data: String,
+    credentials_json: String,
+    options_json: String,
+) -> JsValue {
+    let report = match Options::from_json(&options_json) {
+        Ok(options) => match ensemble(&options, &credentials_json) {
+            Ok(ensemble) => ensemble.recognize_with_report(&img_data).await,
+            Err(e) => Err(e),
+        },
+        Err(e) => Err(e),
+    };
+    match report {
+        Ok((_, report)) => utils::to_js(&report).unwrap_or(JsValue::NULL),
+        Err(e) => {
+            utils::console_log("Error", &e);
+            JsValue::null()
+        }
+    }
+}
+
+/// Like `list_from_handwriting_with_options`, but the tasks go to the
+/// backend chosen by `options.sink`. An empty `list_id` means the
+/// "Einkaufsliste", which is created if it does not exist yet.
+/// For caldav the `token` is json with url, username and password.
+/// Returns the id of the list.
+#[wasm_bindgen]
+pub async fn list_to_sink_from_handwriting(
+    list_id: String,
+    img_data: String,
+    token: String,
+    credentials_json: String,
+    options_json: String,
+) -> JsValue {
+    let token = Secret::from(token);
+    match sink_from_handwriting(list_id, img_data, &token, &credentials_json, &options_json).await {
+        Ok(list_id) => JsValue::from_str(&list_id),
+        Err(e) => {
+            utils::console_log("Error", &e);
+            JsValue::null()
+        }
+    }
+}
+
+/// The lists (projects, calendars) of the backend chosen by `options.sink`.
+#[wasm_bindgen]
+pub async fn get_all_lists(token: String, options_json: String) -> JsValue {
+    let token = Secret::from(token);
+    let lists = match Options::from_json(&options_json) {
+        Ok(options) => match task_sink(options.sink, &token) {
+            Ok(sink) => sink.list_projects().await,
+            Err(e) => Err(e),
+        },
+        Err(e) => Err(e),
+    };
+    match lists {
+        Ok(lists) => utils::to_js(&lists).unwrap_or(JsValue::NULL),
+        Err(e) => {
+            utils::console_log("Error", &e);
+            JsValue::null()
+        }
+    }
+}
+
+/// Recognizes and parses the items and returns them as text in the
+/// `format` ("markdown", "org", "todo_txt", "csv", "json" or "ics"),
+/// without talking to any task service.
+#[wasm_bindgen]
+pub async fn export_from_handwriting(
+    img_data: String,
+    credentials_json: String,
+    options_json: String,
+    format: String,
+) -> JsValue {
+    let exported = match (
+        Options::from_json(&options_json),
+        ExportFormat::from_name(&format),
+    ) {
+        (Ok(options), Ok(format)) => {
+            let context = Context::default();
+            items_from_handwriting(img_data, &credentials_json, &options, false, &context)
                 .await
+                .map(|items| export::render(&items, format, &options.export))
+                .map_err(|e| utils::error_string(&e))
         }
-        Ok(TodoItem::Single(item)) => {
-            make_or_update_project(project_id, std::iter::once(item.as_str()), &todoist_token).await
+        (Err(e), _) | (_, Err(e)) => Err(e),
+    };
+    match exported {
+        Ok(text) => JsValue::from_str(&text),
+        Err(e) => {
+            utils::console_log("Error", &e);
+            JsValue::null()
         }
+    }
+}
+
+async fn sink_from_handwriting(
+    list_id: String,
+    img_data: String,
+    token: &Secret,
+    credentials_json: &str,
+    options_json: &str,
+) -> Result<String, String> {
+    let options = Options::from_json(options_json)?;
+    let sink = task_sink(options.sink, token)?;
+    let shopping_list = list_id.is_empty();
+    let list_id = if shopping_list {
+        sink.ensure_project(todoist::SHOPPING_LIST).await?
+    } else {
+        list_id
+    };
+
+    let spelling = &options.spelling;
+    let history = if spelling.enabled && spelling.use_history {
+        let tasks = sink.find_tasks(&list_id).await.unwrap_or_default();
+        tasks.into_iter().map(|task| task.content).collect()
+    } else {
+        Vec::new()
+    };
+    let context = Context {
+        history,
+        shopping_list,
+    };
+    let items = items_from_handwriting(img_data, credentials_json, &options, false, &context)
+        .await
+        .map_err(|e| utils::error_string(&e))?;
+    sink::upload(sink.as_ref(), &list_id, items, &options).await?;
+    Ok(list_id)
+}
+
+fn task_sink(kind: SinkKind, token: &Secret) -> Result<Box<dyn TaskSink>, String> {
+    let token = token.expose();
+    Ok(match kind {
+        SinkKind::Todoist => Box::new(Todoist::new(token)),
+        SinkKind::MicrosoftTodo => Box::new(MicrosoftTodo::new(token)),
+        SinkKind::GoogleTasks => Box::new(GoogleTasks::new(token)),
+        SinkKind::Caldav => Box::new(CalDav::new(token)?),
+    })
+}
+
+async fn todoist_from_handwriting(
+    project_id: u32,
+    img_data: String,
+    todoist_token: Secret,
+    credentials_json: String,
+    options: Options,
+    single_todo: bool,
+) -> JsValue {
+    utils::console_log("project_id u32", &project_id);
+    let context = pipeline_context(project_id, &todoist_token, &options).await;
+    match items_from_handwriting(img_data, &credentials_json, &options, single_todo, &context).await
+    {
+        Ok(items) => make_or_update_project(project_id, items, &todoist_token, &options).await,
         Err(e) => {
             utils::console_log("Error", &e);
             JsValue::null()
@@ -71,10 +325,211 @@ async fn todoist_from_handwriting(
     }
 }
 
+async fn items_from_handwriting(
+    img_data: String,
+    credentials_json: &str,
+    options: &Options,
+    single_todo: bool,
+    context: &Context,
+) -> Result<Vec<ListItem>, JsValue> {
+    let engine = ocr_engine(options, credentials_json)?;
+    recognize_items(engine.as_ref(), &img_data, options, single_todo, context)
+        .await
+        .map_err(|e| JsValue::from_str(&e))
+}
+
+/// The engine `options.ocr` (or `options.ensemble`) asks for.
+pub fn ocr_engine(options: &Options, credentials_json: &str) -> Result<Box<dyn OcrEngine>, String> {
+    if options.ensemble.is_empty() {
+        provider_engine(options.ocr, options, credentials_json)
+    } else {
+        Ok(Box::new(ensemble(options, credentials_json)?))
+    }
+}
+
+fn provider_engine(
+    provider: OcrProvider,
+    options: &Options,
+    credentials_json: &str,
+) -> Result<Box<dyn OcrEngine>, String> {
+    Ok(match provider {
+        OcrProvider::Google => Box::new(GoogleVision::new(credentials_json, options.google_auth)?),
+        OcrProvider::Azure => Box::new(AzureRead::new(credentials_json)?),
+        OcrProvider::Textract => Box::new(Textract::new(credentials_json)?),
+    })
+}
+
+// the credentials json holds the credentials of every provider
+// by name, e.g. {"google": {...}, "azure": {...}}
+fn ensemble(options: &Options, credentials_json: &str) -> Result<Ensemble, String> {
+    let credentials: HashMap<String, serde_json::Value> =
+        secret::from_credentials_json(credentials_json)?;
+    options
+        .ensemble
+        .iter()
+        .try_fold(Ensemble::new(), |ensemble, provider| {
+            let name = provider.name();
+            let provider_credentials = credentials
+                .get(name)
+                .ok_or_else(|| format!("no credentials for {}", name))?;
+            let engine = provider_engine(*provider, options, &provider_credentials.to_string())?;
+            Ok(ensemble.with_engine(name, engine))
+        })
+}
+
+/// Runs the whole recognition on any `OcrEngine`, without talking to todoist.
+/// With a local engine (see the `tesseract` feature) nothing leaves the machine.
+pub async fn recognize_items(
+    engine: &dyn OcrEngine,
+    img_data: &str,
+    options: &Options,
+    single_todo: bool,
+    context: &Context,
+) -> Result<Vec<ListItem>, String> {
+    let document = engine.recognize(img_data).await?;
+    if single_todo {
+        let largest = document
+            .largest_word()
+            .ok_or("no text found on the image")?;
+        Ok(vec![parse_item(&largest.text, &options.parser)])
+    } else {
+        Ok(lines_to_items(document.lines, options, context))
+    }
+}
+
+// project 0 is the "Einkaufsliste" that gets created on the fly,
+// it has no history yet
+async fn pipeline_context(project_id: u32, todoist_token: &Secret, options: &Options) -> Context {
+    let spelling = &options.spelling;
+    let with_history = spelling.enabled && spelling.use_history && !todoist_token.is_empty();
+    let history = if with_history && project_id != 0 {
+        // the history only improves the spelling, recognize without it
+        fetch_task_contents(project_id, todoist_token)
+            .await
+            .unwrap_or_else(|e| {
+                utils::console_log("Could not load the history", &e);
+                Vec::new()
+            })
+    } else {
+        Vec::new()
+    };
+    Context {
+        history,
+        shopping_list: project_id == 0,
+    }
+}
+
 #[wasm_bindgen]
 pub async fn get_all_projects(todoist_token: String) -> JsValue {
-    match fetch_all_projects(&todoist_token).await {
+    match fetch_all_projects(&Secret::from(todoist_token)).await {
         Some(projects) => projects,
         None => JsValue::NULL,
     }
 }
+
+/// Starts the todoist login: returns `{"url": ..., "state": ...}`, the client
+/// redirects to the url and hands code and state to its backend, which
+/// trades them with `TodoistOAuth::exchange` (the client secret stays there).
+/// An empty `scope` asks for `data:read_write`.
+#[wasm_bindgen]
+pub fn todoist_authorize_url(client_id: String, scope: String) -> JsValue {
+    let scope = if scope.is_empty() {
+        todoist_oauth::DEFAULT_SCOPE
+    } else {
+        &scope
+    };
+    match TodoistOAuth::new(&client_id, "").authorize(scope) {
+        Ok(authorization) => utils::to_js(&authorization).unwrap_or(JsValue::NULL),
+        Err(e) => {
+            utils::console_log("Error", &e);
+            JsValue::null()
+        }
+    }
+}
+
+/// Starts the login with a google account (oauth with pkce): returns
+/// `{"url": ..., "state": ..., "verifier": ...}`, the client redirects to
+/// the url and keeps the rest for `google_exchange_code`.
+/// `scopes` are separated by spaces, empty asks for cloud-vision.
+#[wasm_bindgen]
+pub fn google_consent_url(client_id: String, redirect_uri: String, scopes: String) -> JsValue {
+    let mut scopes: Vec<&str> = scopes.split_whitespace().collect();
+    if scopes.is_empty() {
+        scopes.push(google_oauth::VISION_SCOPE);
+    }
+    match GoogleUserAuth::new(&client_id, None, &redirect_uri).consent(&scopes) {
+        Ok(consent) => utils::to_js(&consent).unwrap_or(JsValue::NULL),
+        Err(e) => {
+            utils::console_log("Error", &e);
+            JsValue::null()
+        }
+    }
+}
+
+/// Trades the code of the redirect for the user's tokens
+/// (`{"access_token", "refresh_token", "expires_at", "scope"}`).
+/// `client_secret` may be empty for clients without one.
+#[wasm_bindgen]
+pub async fn google_exchange_code(
+    client_id: String,
+    client_secret: String,
+    redirect_uri: String,
+    code: String,
+    returned_state: String,
+    consent_json: String,
+) -> JsValue {
+    let client_secret = Secret::from(client_secret);
+    let tokens = match secret::from_credentials_json::<Consent>(&consent_json) {
+        Ok(consent) => {
+            let secret = Some(client_secret.expose()).filter(|s| !s.is_empty());
+            GoogleUserAuth::new(&client_id, secret, &redirect_uri)
+                .exchange(&code, &returned_state, &consent)
+                .await
+        }
+        Err(e) => Err(e),
+    };
+    match tokens {
+        Ok(tokens) => utils::to_js(&tokens).unwrap_or(JsValue::NULL),
+        Err(e) => {
+            utils::console_log("Error", &e);
+            JsValue::null()
+        }
+    }
+}
+
+/// Returns the tokens with a valid access token, refreshed if it
+/// (nearly) expired. The client stores the result for the next call.
+#[wasm_bindgen]
+pub async fn google_fresh_tokens(
+    client_id: String,
+    client_secret: String,
+    tokens_json: String,
+) -> JsValue {
+    let client_secret = Secret::from(client_secret);
+    let tokens = match secret::from_credentials_json::<UserTokens>(&tokens_json) {
+        Ok(tokens) => {
+            let secret = Some(client_secret.expose()).filter(|s| !s.is_empty());
+            GoogleUserAuth::new(&client_id, secret, "")
+                .fresh(&tokens)
+                .await
+        }
+        Err(e) => Err(e),
+    };
+    match tokens {
+        Ok(tokens) => utils::to_js(&tokens).unwrap_or(JsValue::NULL),
+        Err(e) => {
+            utils::console_log("Error", &e);
+            JsValue::null()
+        }
+    }
+}
+
+/// What is wrong with a service-account json, as a list of messages for the
+/// user (empty if nothing). With `test_exchange` google is asked for a token, too.
+#[wasm_bindgen]
+pub async fn validate_credentials(credentials_json: String, test_exchange: bool) -> JsValue {
+    let client = DefaultClient::default();
+    let problems =
+        validate_service_account(&credentials_json, test_exchange.then_some(&client)).await;
+    utils::to_js(&problems).unwrap_or(JsValue::NULL)
+}
@@ -0,0 +1,235 @@
+use crate::http::{DefaultClient, HttpClient, HttpRequest};
+use crate::secret::Secret;
+use crate::sink::{SinkProject, SinkTask, TaskDraft, TaskSink};
+use crate::types::ms_todo::{ChecklistItem, ItemBody, Page, TodoList, TodoTask};
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+
+const GRAPH_URL: &str = "https://graph.microsoft.com/v1.0";
+
+/// Microsoft To Do through the graph api, authenticated with an
+/// access token that has the `Tasks.ReadWrite` scope.
+/// Subtasks become steps (checklist items) of their parent task,
+/// steps cannot be nested, so deeper items are steps of the top task.
+pub struct MicrosoftTodo<C> {
+    token: Secret,
+    client: C,
+    pub base_url: String,
+}
+
+impl MicrosoftTodo<DefaultClient> {
+    pub fn new(token: &str) -> Self {
+        MicrosoftTodo::with_client(token, DefaultClient::default())
+    }
+}
+
+impl<C: HttpClient> MicrosoftTodo<C> {
+    pub fn with_client(token: &str, client: C) -> Self {
+        MicrosoftTodo {
+            token: Secret::from(token),
+            client,
+            base_url: GRAPH_URL.to_string(),
+        }
+    }
+
+    fn request(&self, method: &str, url: &str) -> HttpRequest {
+        HttpRequest::new(method, url).bearer(&self.token)
+    }
+
+    async fn post<T: DeserializeOwned>(&self, url: &str, body: Vec<u8>) -> Result<T, String> {
+        let request = self
+            .request("POST", url)
+            .header("Content-Type", "application/json")
+            .body(body);
+        self.client.send(request).await?.into_json()
+    }
+
+    // follows the nextLink of every page
+    async fn get_all<T: DeserializeOwned>(&self, url: &str) -> Result<Vec<T>, String> {
+        let mut all = Vec::new();
+        let mut next = Some(url.to_string());
+        while let Some(url) = next {
+            let page: Page<T> = self
+                .client
+                .send(self.request("GET", &url))
+                .await?
+                .into_json()?;
+            all.extend(page.value);
+            next = page.next_link;
+        }
+        Ok(all)
+    }
+}
+
+#[async_trait(?Send)]
+impl<C: HttpClient> TaskSink for MicrosoftTodo<C> {
+    async fn list_projects(&self) -> Result<Vec<SinkProject>, String> {
+        let lists: Vec<TodoList> = self
+            .get_all(&format!("{}/me/todo/lists", self.base_url))
+            .await?;
+        Ok(lists
+            .into_iter()
+            .map(|list| SinkProject {
+                id: list.id,
+                name: list.display_name,
+            })
+            .collect())
+    }
+
+    async fn ensure_project(&self, name: &str) -> Result<String, String> {
+        let lists = self.list_projects().await?;
+        if let Some(list) = lists.iter().find(|l| l.name.eq_ignore_ascii_case(name)) {
+            return Ok(list.id.clone());
+        }
+        let list = TodoList {
+            display_name: name.to_string(),
+            ..TodoList::default()
+        };
+        let body = serde_json::to_vec(&list).map_err(|e| e.to_string())?;
+        let created: TodoList = self
+            .post(&format!("{}/me/todo/lists", self.base_url), body)
+            .await?;
+        Ok(created.id)
+    }
+
+    async fn create_task(&self, project_id: &str, draft: &TaskDraft) -> Result<String, String> {
+        let item = draft.item;
+        let tasks_url = format!("{}/me/todo/lists/{}/tasks", self.base_url, project_id);
+
+        // the ids of steps are "<task id>/<step id>"
+        if let Some(parent_id) = &draft.parent_id {
+            let task_id = parent_id.split('/').next().unwrap_or(parent_id);
+            let step = ChecklistItem {
+                display_name: item.content.clone(),
+                is_checked: draft.completed,
+                ..ChecklistItem::default()
+            };
+            let body = serde_json::to_vec(&step).map_err(|e| e.to_string())?;
+            let created: ChecklistItem = self
+                .post(&format!("{}/{}/checklistItems", tasks_url, task_id), body)
+                .await?;
+            return Ok(format!("{}/{}", task_id, created.id));
+        }
+
+        let task = TodoTask {
+            title: item.content.clone(),
+            body: item.description.as_ref().map(|description| ItemBody {
+                content: description.clone(),
+                content_type: "text".to_string(),
+            }),
+            importance: item
+                .priority
+                .filter(|p| *p >= 3)
+                .map(|_| "high".to_string()),
+            status: draft.completed.then(|| "completed".to_string()),
+            categories: item.labels.clone(),
+            ..TodoTask::default()
+        };
+        let body = serde_json::to_vec(&task).map_err(|e| e.to_string())?;
+        let created: TodoTask = self.post(&tasks_url, body).await?;
+        Ok(created.id)
+    }
+
+    async fn find_tasks(&self, project_id: &str) -> Result<Vec<SinkTask>, String> {
+        let tasks: Vec<TodoTask> = self
+            .get_all(&format!(
+                "{}/me/todo/lists/{}/tasks",
+                self.base_url, project_id
+            ))
+            .await?;
+        Ok(tasks
+            .into_iter()
+            .map(|task| SinkTask {
+                id: task.id,
+                content: task.title,
+            })
+            .collect())
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod test {
+
+    use super::MicrosoftTodo;
+    use crate::http::{HttpResponse, MockServer, NativeClient};
+    use crate::options::Options;
+    use crate::parser::ListItem;
+    use crate::sink::{upload, TaskSink};
+    use futures::executor::block_on;
+
+    fn sink(server: &MockServer) -> MicrosoftTodo<NativeClient> {
+        let mut sink = MicrosoftTodo::with_client("graph-token", NativeClient::default());
+        sink.base_url = server.url.clone();
+        sink
+    }
+
+    #[test]
+    fn existing_list_is_found_across_pages() {
+        let server = MockServer::start_with(|url| {
+            vec![
+                HttpResponse::with_json(
+                    200,
+                    &format!(
+                        r#"{{"value": [{{"id": "AAA", "displayName": "Aufgaben"}}],
+                            "@odata.nextLink": "{}/me/todo/lists?$skiptoken=2"}}"#,
+                        url
+                    ),
+                ),
+                HttpResponse::with_json(
+                    200,
+                    r#"{"value": [{"id": "BBB", "displayName": "Einkaufsliste"}]}"#,
+                ),
+            ]
+        });
+        let sink = sink(&server);
+
+        let id = block_on(sink.ensure_project("einkaufsliste"));
+        let requests = server.requests();
+
+        assert_eq!(Ok("BBB".to_string()), id);
+        assert_eq!(2, requests.len());
+        assert_eq!("/me/todo/lists?$skiptoken=2", requests[1].url);
+    }
+
+    #[test]
+    fn subtasks_become_steps() {
+        let server = MockServer::start(vec![
+            HttpResponse::with_json(201, r#"{"id": "T1", "title": "Party"}"#),
+            HttpResponse::with_json(
+                201,
+                r#"{"id": "S1", "displayName": "Chips", "isChecked": false}"#,
+            ),
+        ]);
+        let sink = sink(&server);
+        let items = vec![
+            ListItem {
+                content: "Party".to_string(),
+                priority: Some(4),
+                labels: vec!["Feier".to_string()],
+                ..ListItem::default()
+            },
+            ListItem {
+                content: "Chips".to_string(),
+                parent: Some(0),
+                ..ListItem::default()
+            },
+        ];
+
+        let ids = block_on(upload(&sink, "L1", items, &Options::default()))
+            .expect("test fails: mock server answers");
+        let requests = server.requests();
+
+        assert_eq!(vec![Some("T1".to_string()), Some("T1/S1".to_string())], ids);
+        assert_eq!("/me/todo/lists/L1/tasks", requests[0].url);
+        assert!(requests[0].headers.contains(&(
+            "authorization".to_string(),
+            "Bearer graph-token".to_string()
+        )));
+        let task: serde_json::Value = serde_json::from_slice(&requests[0].body).unwrap();
+        assert_eq!(
+            serde_json::json!({"title": "Party", "importance": "high", "categories": ["Feier"]}),
+            task
+        );
+        assert_eq!("/me/todo/lists/L1/tasks/T1/checklistItems", requests[1].url);
+    }
+}
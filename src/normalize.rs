@@ -0,0 +1,251 @@
+use serde::Deserialize;
+
+/// Rules that clean up raw OCR lines before they are parsed into items.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct NormalizeConfig {
+    pub strip_bullets: bool,
+    pub bullets: Vec<char>,
+    // "1.", "2)", "a)", "(3)"
+    pub strip_enumerations: bool,
+    // single letters a drawn checkbox is often misread as, stripped from
+    // every line, so none by default ("O Tannenbaum"); `detect_checkbox`
+    // already takes these letters if they look like a box
+    pub checkbox_letters: Vec<String>,
+    pub collapse_whitespace: bool,
+    // empty lines and lines without any letter or digit
+    pub drop_empty: bool,
+    // "B0hnen" -> "Bohnen", "Mi1ch" -> "Milch"
+    pub fix_ocr_confusions: bool,
+}
+
+impl Default for NormalizeConfig {
+    fn default() -> Self {
+        NormalizeConfig {
+            strip_bullets: true,
+            // '*' is left out on purpose, "**" marks a priority (see `strip_star`)
+            bullets: vec![
+                '-', '–', '—', '•', '·', '◦', '▪', '‣', '→', '>', '☐', '□', '○',
+            ],
+            strip_enumerations: true,
+            checkbox_letters: Vec::new(),
+            collapse_whitespace: true,
+            drop_empty: true,
+            fix_ocr_confusions: true,
+        }
+    }
+}
+
+/// Cleans up a single line. Returns `None` if the line should be dropped.
+pub fn normalize_line(line: &str, config: &NormalizeConfig) -> Option<String> {
+    let mut line = line.trim().to_string();
+
+    // a bullet can come before an enumeration ("- 1. Milch") and vice versa
+    loop {
+        let before = line.len();
+        if config.strip_bullets {
+            line = strip_bullet(&line, &config.bullets).to_string();
+            line = strip_star(&line).to_string();
+        }
+        if config.strip_enumerations {
+            line = strip_enumeration(&line).to_string();
+        }
+        line = strip_checkbox_letter(&line, &config.checkbox_letters).to_string();
+        if line.len() == before {
+            break;
+        }
+    }
+
+    if config.fix_ocr_confusions {
+        line = line
+            .split(' ')
+            .map(fix_confusions)
+            .collect::<Vec<_>>()
+            .join(" ");
+    }
+    if config.collapse_whitespace {
+        line = line.split_whitespace().collect::<Vec<_>>().join(" ");
+    }
+    if config.drop_empty && !line.chars().any(char::is_alphanumeric) {
+        return None;
+    }
+    Some(line)
+}
+
+fn strip_bullet<'a>(line: &'a str, bullets: &[char]) -> &'a str {
+    match line.strip_prefix(bullets) {
+        // "-5 Grad" is no bullet, "-Milch" and "- Milch" are
+        Some(rest) if !rest.starts_with(|c: char| c.is_ascii_digit()) => rest.trim_start(),
+        _ => line,
+    }
+}
+
+// a single '*' is a bullet, a run of them ("** Brot") is a priority
+fn strip_star(line: &str) -> &str {
+    match line.strip_prefix('*') {
+        Some(rest) if !rest.starts_with('*') => rest.trim_start(),
+        _ => line,
+    }
+}
+
+fn strip_enumeration(line: &str) -> &str {
+    let Some((first, rest)) = line.split_once(char::is_whitespace) else {
+        return line;
+    };
+    let inner = first.strip_prefix('(').unwrap_or(first);
+    let Some(marker) = inner.strip_suffix(['.', ')']) else {
+        return line;
+    };
+    let is_number =
+        !marker.is_empty() && marker.len() <= 3 && marker.chars().all(|c| c.is_ascii_digit());
+    let is_letter = marker.chars().count() == 1 && marker.chars().all(char::is_alphabetic);
+    if is_number || is_letter {
+        rest.trim_start()
+    } else {
+        line
+    }
+}
+
+fn strip_checkbox_letter<'a>(line: &'a str, letters: &[String]) -> &'a str {
+    match line.split_once(char::is_whitespace) {
+        Some((first, rest)) if letters.iter().any(|l| l == first) => rest.trim_start(),
+        _ => line,
+    }
+}
+
+// Replaces '0' and '1' that sit between letters, and a '0' at the start
+// of a word, by the letters they are usually confused with.
+// Words with other digits are numbers or amounts and stay as they are.
+fn fix_confusions(word: &str) -> String {
+    let chars: Vec<char> = word.chars().collect();
+    if chars
+        .iter()
+        .any(|c| c.is_ascii_digit() && *c != '0' && *c != '1')
+    {
+        return word.to_string();
+    }
+    let is_letter = |i: Option<usize>| {
+        i.and_then(|i| chars.get(i))
+            .is_some_and(|c| c.is_alphabetic())
+    };
+    let is_upper = |i: Option<usize>| {
+        i.and_then(|i| chars.get(i))
+            .is_some_and(|c| c.is_uppercase())
+    };
+
+    chars
+        .iter()
+        .enumerate()
+        .map(|(i, &c)| {
+            let before = i.checked_sub(1);
+            let after = Some(i + 1);
+            let between_letters = is_letter(before) && is_letter(after);
+            let word_start = i == 0 && is_letter(after);
+            let shouting = is_upper(before) && is_upper(after);
+            match c {
+                '0' if between_letters || word_start => {
+                    if shouting || word_start {
+                        'O'
+                    } else {
+                        'o'
+                    }
+                }
+                '1' if between_letters => {
+                    if shouting {
+                        'I'
+                    } else {
+                        'l'
+                    }
+                }
+                c => c,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+
+    extern crate wasm_bindgen_test;
+
+    use wasm_bindgen_test::*;
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    use super::{normalize_line, NormalizeConfig};
+
+    fn normalize(line: &str) -> Option<String> {
+        normalize_line(line, &NormalizeConfig::default())
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn bullets_are_stripped() {
+        assert_eq!(Some("Milch".to_string()), normalize("- Milch"));
+        assert_eq!(Some("Milch".to_string()), normalize("•Milch"));
+        assert_eq!(Some("Milch".to_string()), normalize("☐ Milch"));
+        assert_eq!(Some("-5 Grad".to_string()), normalize("-5 Grad"));
+        assert_eq!(Some("Milch".to_string()), normalize("* Milch"));
+        assert_eq!(Some("** Milch".to_string()), normalize("** Milch"));
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn enumerations_are_stripped() {
+        assert_eq!(Some("Brot".to_string()), normalize("1. Brot"));
+        assert_eq!(Some("Brot".to_string()), normalize("12) Brot"));
+        assert_eq!(Some("Brot".to_string()), normalize("a) Brot"));
+        assert_eq!(Some("Brot".to_string()), normalize("(3) Brot"));
+        assert_eq!(Some("Brot".to_string()), normalize("- 2. Brot"));
+        assert_eq!(Some("2x Brot".to_string()), normalize("2x Brot"));
+        assert_eq!(Some("1.5 kg Mehl".to_string()), normalize("1.5 kg Mehl"));
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn misread_checkboxes_are_stripped() {
+        let config: NormalizeConfig = serde_json::from_str(r#"{"checkbox_letters": ["O", "D"]}"#)
+            .expect("test fails: config must deserialize");
+        let normalize = |line| normalize_line(line, &config);
+        assert_eq!(Some("Eier".to_string()), normalize("O Eier"));
+        assert_eq!(Some("Eier".to_string()), normalize("D Eier"));
+        assert_eq!(Some("Oma anrufen".to_string()), normalize("Oma anrufen"));
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn leading_letters_are_text_by_default() {
+        assert_eq!(Some("O Tannenbaum".to_string()), normalize("O Tannenbaum"));
+        assert_eq!(Some("D Zug".to_string()), normalize("D Zug"));
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn whitespace_is_collapsed_and_empty_lines_dropped() {
+        assert_eq!(
+            Some("Tomaten Dose".to_string()),
+            normalize("  Tomaten \t Dose ")
+        );
+        assert_eq!(None, normalize("   "));
+        assert_eq!(None, normalize("- . ,"));
+        assert_eq!(None, normalize("•"));
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn ocr_confusions_are_fixed() {
+        assert_eq!(Some("Bohnen".to_string()), normalize("B0hnen"));
+        assert_eq!(Some("Obst".to_string()), normalize("0bst"));
+        assert_eq!(Some("Milch".to_string()), normalize("Mi1ch"));
+        assert_eq!(Some("HOLZ".to_string()), normalize("H0LZ"));
+        assert_eq!(Some("10er Pack".to_string()), normalize("10er Pack"));
+        assert_eq!(Some("100g Käse".to_string()), normalize("100g Käse"));
+        assert_eq!(Some("A4 Papier".to_string()), normalize("A4 Papier"));
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn rules_can_be_turned_off() {
+        let config: NormalizeConfig = serde_json::from_str(
+            r#"{"strip_bullets": false, "checkbox_letters": [], "fix_ocr_confusions": false}"#,
+        )
+        .expect("test fails: config must deserialize");
+        assert_eq!(
+            Some("- B0hnen".to_string()),
+            normalize_line("- B0hnen", &config)
+        );
+        assert_eq!(Some("Eier".to_string()), normalize_line("1. Eier", &config));
+    }
+}
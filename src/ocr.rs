@@ -0,0 +1,87 @@
+use crate::layout::{TextLine, WordBox};
+use async_trait::async_trait;
+use serde::Deserialize;
+
+/// What an ocr-provider recognized on an image, independent of the provider.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Document {
+    // the recognized text, one line per line of handwriting
+    pub text: String,
+    pub lines: Vec<TextLine>,
+}
+
+impl Document {
+    /// The word written in the biggest letters, used when only a single todo
+    /// is wanted from an image (e.g. a label on a bottle).
+    pub fn largest_word(&self) -> Option<&WordBox> {
+        self.lines
+            .iter()
+            .flat_map(|line| &line.words)
+            .max_by_key(|word| word.height)
+    }
+}
+
+/// Which engine recognizes the image on the wasm-side,
+/// the credentials json has to match it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OcrProvider {
+    // a google service-account json
+    #[default]
+    Google,
+    // {"endpoint": "...", "subscription_key": "..."}
+    Azure,
+    // {"access_key_id": "...", "secret_access_key": "...", "region": "..."}
+    Textract,
+}
+
+impl OcrProvider {
+    pub fn name(&self) -> &'static str {
+        match self {
+            OcrProvider::Google => "google",
+            OcrProvider::Azure => "azure",
+            OcrProvider::Textract => "textract",
+        }
+    }
+}
+
+/// Turns a (base64 encoded) image into a `Document`.
+/// The futures are not `Send`, as the engines that talk to an api
+/// run on the single threaded wasm-side.
+#[async_trait(?Send)]
+pub trait OcrEngine {
+    async fn recognize(&self, img_data: &str) -> Result<Document, String>;
+}
+
+#[cfg(test)]
+mod test {
+
+    extern crate wasm_bindgen_test;
+
+    use wasm_bindgen_test::*;
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    use super::Document;
+    use crate::layout::{TextLine, WordBox};
+
+    fn word(text: &str, height: u32) -> WordBox {
+        WordBox {
+            text: text.to_string(),
+            height,
+            ..WordBox::default()
+        }
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn largest_word_is_the_tallest() {
+        let document = Document {
+            text: "Mythos\nBier 0,5l".to_string(),
+            lines: vec![
+                TextLine::from_words("Mythos", vec![word("Mythos", 80)]),
+                TextLine::from_words("Bier 0,5l", vec![word("Bier", 20), word("0,5l", 18)]),
+            ],
+        };
+        assert_eq!("Mythos", document.largest_word().unwrap().text);
+        assert_eq!(None, Document::default().largest_word());
+    }
+}
@@ -0,0 +1,85 @@
+use crate::checkbox::CheckboxConfig;
+use crate::credentials::GoogleAuth;
+use crate::export::ExportConfig;
+use crate::grocery::GroceryConfig;
+use crate::layout::LayoutConfig;
+use crate::normalize::NormalizeConfig;
+use crate::ocr::OcrProvider;
+use crate::parser::ParserConfig;
+use crate::shopping::ShoppingConfig;
+use crate::spelling::SpellingConfig;
+use crate::translate::TranslateConfig;
+use serde::Deserialize;
+
+/// Everything a client can tweak, passed in as json from the wasm-side.
+/// Missing fields fall back to their defaults.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Options {
+    pub ocr: OcrProvider,
+    // two or more providers that vote per line, `ocr` is ignored then
+    pub ensemble: Vec<OcrProvider>,
+    // how a google service-account gets its token
+    pub google_auth: GoogleAuth,
+    pub normalize: NormalizeConfig,
+    pub parser: ParserConfig,
+    pub spelling: SpellingConfig,
+    pub translate: TranslateConfig,
+    pub layout: LayoutConfig,
+    pub shopping: ShoppingConfig,
+    pub grocery: GroceryConfig,
+    pub checkbox: CheckboxConfig,
+    pub labels: LabelPolicy,
+    pub sink: SinkKind,
+    pub export: ExportConfig,
+}
+
+/// What happens with parsed labels that do not exist in the todoist account.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LabelPolicy {
+    // send the names as they are
+    #[default]
+    AsIs,
+    // create missing labels through the labels endpoint first
+    AutoCreate,
+    // drop every label that is not yet in the account
+    AllowList,
+}
+
+/// Where the tasks are created.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SinkKind {
+    #[default]
+    Todoist,
+    // the token is an access token for the graph api
+    MicrosoftTodo,
+    // the token is an oauth access token of the user
+    GoogleTasks,
+    // the token is json with url, username and password
+    Caldav,
+}
+
+impl Options {
+    pub fn from_json(options_json: &str) -> Result<Self, String> {
+        if options_json.trim().is_empty() {
+            return Ok(Options::default());
+        }
+        let options: Options = serde_json::from_str(options_json).map_err(|e| e.to_string())?;
+        // todoist and icalendar only know these
+        if let Some(marker) = options
+            .parser
+            .priority
+            .markers
+            .iter()
+            .find(|m| !(1..=4).contains(&m.priority))
+        {
+            return Err(format!(
+                "the priority of `{}` is {}, it has to be 1 to 4",
+                marker.marker, marker.priority
+            ));
+        }
+        Ok(options)
+    }
+}
@@ -0,0 +1,457 @@
+use crate::checkbox::CheckboxState;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+/// One entry of a handwritten list, ready to be turned into a todoist-task.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ListItem {
+    pub content: String,
+    // todoist priority: 1 (normal) to 4 (urgent)
+    pub priority: Option<u8>,
+    // label names, not ids (REST v2)
+    pub labels: Vec<String>,
+    // index of the parent item within the same list
+    pub parent: Option<usize>,
+    // name of the heading the item was written under
+    pub section: Option<String>,
+    pub description: Option<String>,
+    // a checkbox drawn in front of the item on paper
+    pub checkbox: Option<CheckboxState>,
+    // the content as it was recognized, if it got corrected
+    pub original: Option<String>,
+    // store category on a shopping list ("dairy", "bakery"..)
+    pub category: Option<String>,
+    // a date written on the line, as "YYYY-MM-DD"
+    pub due: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ParserConfig {
+    pub priority: PriorityGrammar,
+    pub labels: LabelGrammar,
+    pub due: DueGrammar,
+}
+
+/// Describes which markers on paper stand for which todoist priority.
+///
+/// Todoist counts the other way round than its UI does:
+/// "p1" (most urgent) is priority 4, "p4" is priority 1.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct PriorityGrammar {
+    pub markers: Vec<PriorityMarker>,
+    // understand "p1".."p4" tokens
+    pub p_tokens: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PriorityMarker {
+    pub marker: String,
+    // 1 (normal) to 4 (urgent)
+    pub priority: u8,
+    #[serde(default)]
+    pub position: MarkerPosition,
+}
+
+/// Where on the line a marker counts.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MarkerPosition {
+    #[default]
+    Both,
+    Leading,
+    // e.g. a single "*", which in front of the item is a bullet
+    Trailing,
+}
+
+impl PriorityMarker {
+    fn new(marker: &str, priority: u8) -> Self {
+        PriorityMarker {
+            marker: marker.to_string(),
+            priority,
+            position: MarkerPosition::Both,
+        }
+    }
+
+    fn trailing(marker: &str, priority: u8) -> Self {
+        PriorityMarker {
+            position: MarkerPosition::Trailing,
+            ..PriorityMarker::new(marker, priority)
+        }
+    }
+
+    fn counts_at(&self, position: MarkerPosition) -> bool {
+        self.position == MarkerPosition::Both || self.position == position
+    }
+}
+
+impl Default for PriorityGrammar {
+    fn default() -> Self {
+        PriorityGrammar {
+            markers: vec![
+                PriorityMarker::new("!!!", 4),
+                PriorityMarker::new("!!", 3),
+                PriorityMarker::new("!", 2),
+                PriorityMarker::new("***", 4),
+                PriorityMarker::new("**", 3),
+                PriorityMarker::trailing("*", 2),
+            ],
+            p_tokens: true,
+        }
+    }
+}
+
+/// Words starting with one of the `prefixes` (e.g. "#dm", "@errand")
+/// become todoist labels. An empty list turns label parsing off.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct LabelGrammar {
+    pub prefixes: Vec<char>,
+}
+
+impl Default for LabelGrammar {
+    fn default() -> Self {
+        LabelGrammar {
+            prefixes: vec!['#', '@'],
+        }
+    }
+}
+
+/// Words that are a date in one of the `formats` (chrono syntax) become
+/// the due date of the item. An empty list turns date parsing off.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct DueGrammar {
+    pub formats: Vec<String>,
+}
+
+impl Default for DueGrammar {
+    fn default() -> Self {
+        DueGrammar {
+            formats: vec!["%Y-%m-%d".to_string(), "%d.%m.%Y".to_string()],
+        }
+    }
+}
+
+pub fn parse_item(line: &str, config: &ParserConfig) -> ListItem {
+    let (line, labels) = extract_labels(line, &config.labels);
+    let (line, due) = extract_due(&line, &config.due);
+    let (content, priority) = extract_priority(&line, &config.priority);
+    ListItem {
+        content,
+        priority,
+        labels,
+        due,
+        ..ListItem::default()
+    }
+}
+
+/// Removes the first date-word from the line and returns it as "YYYY-MM-DD".
+pub fn extract_due(line: &str, grammar: &DueGrammar) -> (String, Option<String>) {
+    let mut due = None;
+    let mut words = Vec::new();
+    for word in line.split_whitespace() {
+        if due.is_none() {
+            let date = grammar
+                .formats
+                .iter()
+                .find_map(|format| NaiveDate::parse_from_str(word, format).ok());
+            if let Some(date) = date {
+                due = Some(date.format("%Y-%m-%d").to_string());
+                continue;
+            }
+        }
+        words.push(word);
+    }
+    (words.join(" "), due)
+}
+
+/// Removes every label-word from the line and returns the label names.
+/// Whatever trails the name inside the word (e.g. "!!" in "#dm!!")
+/// stays in the line, so that other markers are still found.
+pub fn extract_labels(line: &str, grammar: &LabelGrammar) -> (String, Vec<String>) {
+    let mut labels: Vec<String> = Vec::new();
+    let mut words = Vec::new();
+    for word in line.split_whitespace() {
+        match split_label(word, grammar) {
+            Some((label, rest)) => {
+                if !labels.iter().any(|l| l.eq_ignore_ascii_case(label)) {
+                    labels.push(label.to_string());
+                }
+                if !rest.is_empty() {
+                    words.push(rest);
+                }
+            }
+            None => words.push(word),
+        }
+    }
+    (words.join(" "), labels)
+}
+
+fn split_label<'a>(word: &'a str, grammar: &LabelGrammar) -> Option<(&'a str, &'a str)> {
+    let mut chars = word.chars();
+    let prefix = chars.next()?;
+    if !grammar.prefixes.contains(&prefix) {
+        return None;
+    }
+    let name = chars.as_str();
+    let name_end = name
+        .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '-'))
+        .unwrap_or(name.len());
+    if name_end == 0 {
+        return None;
+    }
+    Some((&name[..name_end], &name[name_end..]))
+}
+
+/// Strips leading and trailing priority markers from the line.
+/// If both ends carry a marker, the higher priority wins.
+pub fn extract_priority(line: &str, grammar: &PriorityGrammar) -> (String, Option<u8>) {
+    let mut rest = line.trim();
+    let mut priority = None;
+
+    if let Some((p, stripped)) = leading_priority(rest, grammar) {
+        priority = Some(p);
+        rest = stripped;
+    }
+    if let Some((p, stripped)) = trailing_priority(rest, grammar) {
+        priority = priority.max(Some(p));
+        rest = stripped;
+    }
+    (rest.to_string(), priority)
+}
+
+fn leading_priority<'a>(line: &'a str, grammar: &PriorityGrammar) -> Option<(u8, &'a str)> {
+    if grammar.p_tokens {
+        if let Some((token, rest)) = line.split_once(char::is_whitespace) {
+            if let Some(p) = p_token(token) {
+                return Some((p, rest.trim_start()));
+            }
+        }
+    }
+    let run_end = line
+        .find(|c: char| !is_marker_char(c))
+        .unwrap_or(line.len());
+    let priority = marker_priority(&line[..run_end], grammar, MarkerPosition::Leading)?;
+    Some((priority, line[run_end..].trim_start()))
+}
+
+fn trailing_priority<'a>(line: &'a str, grammar: &PriorityGrammar) -> Option<(u8, &'a str)> {
+    if grammar.p_tokens {
+        if let Some((rest, token)) = line.rsplit_once(char::is_whitespace) {
+            if let Some(p) = p_token(token) {
+                return Some((p, rest.trim_end()));
+            }
+        }
+    }
+    let run_start = line
+        .rfind(|c: char| !is_marker_char(c))
+        .map(|i| i + line[i..].chars().next().map_or(0, char::len_utf8))
+        .unwrap_or(0);
+    let priority = marker_priority(&line[run_start..], grammar, MarkerPosition::Trailing)?;
+    Some((priority, line[..run_start].trim_end()))
+}
+
+fn is_marker_char(c: char) -> bool {
+    !c.is_alphanumeric() && !c.is_whitespace()
+}
+
+// "p1" is the most urgent todoist priority, which is 4 in the api
+fn p_token(token: &str) -> Option<u8> {
+    let mut chars = token.chars();
+    match (chars.next(), chars.next(), chars.next()) {
+        (Some('p' | 'P'), Some(n @ '1'..='4'), None) => Some(5 - n.to_digit(10)? as u8),
+        _ => None,
+    }
+}
+
+// The symbol-run has to be a marker, or start with the longest marker
+// that fits (e.g. "!!!!" is still understood as "!!!")
+fn marker_priority(run: &str, grammar: &PriorityGrammar, position: MarkerPosition) -> Option<u8> {
+    if run.is_empty() {
+        return None;
+    }
+    let markers = grammar.markers.iter().filter(|m| m.counts_at(position));
+    markers
+        .clone()
+        .find(|m| m.marker == run)
+        .or_else(|| {
+            markers
+                .filter(|m| !m.marker.is_empty() && run.starts_with(&m.marker))
+                .max_by_key(|m| m.marker.len())
+        })
+        .map(|m| m.priority)
+}
+
+#[cfg(test)]
+mod test {
+
+    extern crate wasm_bindgen_test;
+
+    use wasm_bindgen_test::*;
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    use super::{
+        extract_due, extract_labels, extract_priority, parse_item, DueGrammar, LabelGrammar,
+        ParserConfig, PriorityGrammar,
+    };
+    use crate::options::Options;
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn plain_item_has_no_priority() {
+        let item = parse_item("  Milch ", &ParserConfig::default());
+        assert_eq!("Milch", item.content);
+        assert_eq!(None, item.priority);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn exclamation_marks_are_stripped_and_mapped() {
+        let grammar = PriorityGrammar::default();
+        assert_eq!(
+            ("Milch".to_string(), Some(2)),
+            extract_priority("Milch!", &grammar)
+        );
+        assert_eq!(
+            ("Milch".to_string(), Some(3)),
+            extract_priority("!! Milch", &grammar)
+        );
+        assert_eq!(
+            ("Milch".to_string(), Some(4)),
+            extract_priority("Milch !!!!", &grammar)
+        );
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn stars_are_stripped_and_mapped() {
+        let grammar = PriorityGrammar::default();
+        assert_eq!(
+            ("Brot".to_string(), Some(2)),
+            extract_priority("Brot *", &grammar)
+        );
+        assert_eq!(
+            ("Brot".to_string(), Some(3)),
+            extract_priority("**Brot", &grammar)
+        );
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn a_leading_star_is_no_priority() {
+        let item = parse_item("* Milch", &ParserConfig::default());
+        assert_eq!(None, item.priority);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn p_tokens_use_todoist_order() {
+        let grammar = PriorityGrammar::default();
+        assert_eq!(
+            ("Steuer machen".to_string(), Some(4)),
+            extract_priority("p1 Steuer machen", &grammar)
+        );
+        assert_eq!(
+            ("Steuer machen".to_string(), Some(1)),
+            extract_priority("Steuer machen P4", &grammar)
+        );
+        assert_eq!(
+            ("p5 ist kein Token".to_string(), None),
+            extract_priority("p5 ist kein Token", &grammar)
+        );
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn higher_marker_wins_and_punctuation_stays() {
+        let grammar = PriorityGrammar::default();
+        assert_eq!(
+            ("Party".to_string(), Some(3)),
+            extract_priority("! Party !!", &grammar)
+        );
+        assert_eq!(
+            ("Party:".to_string(), None),
+            extract_priority("Party:", &grammar)
+        );
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn grammar_is_configurable() {
+        let config: ParserConfig = serde_json::from_str(
+            r#"{"priority":{"markers":[{"marker":"+","priority":4}],"p_tokens":false}}"#,
+        )
+        .expect("test fails: config must deserialize");
+        let item = parse_item("+ Zahnarzt p1", &config);
+        assert_eq!("Zahnarzt p1", item.content);
+        assert_eq!(Some(4), item.priority);
+        assert_eq!(None, parse_item("Zahnarzt!", &config).priority);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn priorities_are_checked_when_the_options_are_read() {
+        let error = Options::from_json(
+            r#"{"parser": {"priority": {"markers": [{"marker": "+", "priority": 9}]}}}"#,
+        )
+        .expect_err("test fails: 9 is no todoist priority");
+        assert_eq!("the priority of `+` is 9, it has to be 1 to 4", error);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn labels_are_stripped_from_content() {
+        let grammar = LabelGrammar::default();
+        assert_eq!(
+            (
+                "Zahnpasta kaufen".to_string(),
+                vec!["dm".to_string(), "errand".to_string()]
+            ),
+            extract_labels("Zahnpasta #dm kaufen @errand", &grammar)
+        );
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn lone_prefixes_and_duplicates_are_no_labels() {
+        let grammar = LabelGrammar::default();
+        assert_eq!(
+            ("Schrauben # 4".to_string(), vec!["baumarkt".to_string()]),
+            extract_labels("#baumarkt Schrauben # 4 #Baumarkt", &grammar)
+        );
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn labels_and_priority_combine() {
+        let item = parse_item("Milch #dm!!", &ParserConfig::default());
+        assert_eq!("Milch", item.content);
+        assert_eq!(Some(3), item.priority);
+        assert_eq!(vec!["dm".to_string()], item.labels);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn label_parsing_can_be_turned_off() {
+        let config: ParserConfig = serde_json::from_str(r#"{"labels":{"prefixes":[]}}"#)
+            .expect("test fails: config must deserialize");
+        let item = parse_item("Mail an @anna", &config);
+        assert_eq!("Mail an @anna", item.content);
+        assert!(item.labels.is_empty());
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn dates_become_the_due_date() {
+        let grammar = DueGrammar::default();
+        assert_eq!(
+            ("Steuer abgeben".to_string(), Some("2024-05-31".to_string())),
+            extract_due("Steuer 31.05.2024 abgeben", &grammar)
+        );
+        assert_eq!(
+            ("Zahnarzt".to_string(), Some("2024-06-03".to_string())),
+            extract_due("Zahnarzt 2024-06-03", &grammar)
+        );
+        assert_eq!(
+            ("Pizza 31.02.2024".to_string(), None),
+            extract_due("Pizza 31.02.2024", &grammar)
+        );
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn due_dates_combine_with_priority() {
+        let item = parse_item("!! Miete 01.06.2024", &ParserConfig::default());
+        assert_eq!("Miete", item.content);
+        assert_eq!(Some(3), item.priority);
+        assert_eq!(Some("2024-06-01".to_string()), item.due);
+    }
+}
@@ -0,0 +1,58 @@
+use crate::checkbox::detect_checkbox;
+use crate::grocery;
+use crate::layout::{nest_items, split_headings, TextLine};
+use crate::normalize::normalize_line;
+use crate::options::Options;
+use crate::parser::{parse_item, ListItem};
+use crate::shopping;
+use crate::spelling::Corrector;
+use crate::translate;
+
+/// What the pipeline knows about the target of the items.
+#[derive(Debug, Default)]
+pub struct Context {
+    // contents of the tasks already in the target project,
+    // the spelling correction learns from them
+    pub history: Vec<String>,
+    // the items go into a newly created "Einkaufsliste"
+    pub shopping_list: bool,
+}
+
+/// Runs all stages that turn recognized lines into list items.
+pub fn lines_to_items(lines: Vec<TextLine>, options: &Options, context: &Context) -> Vec<ListItem> {
+    // headings come first, underlines would not survive the normalisation,
+    // and neither would checkboxes
+    let (lines, marks): (Vec<_>, Vec<_>) = split_headings(lines, &options.layout)
+        .into_iter()
+        .filter_map(|(line, section)| {
+            let (checkbox, text) = detect_checkbox(&line);
+            let text = normalize_line(&text, &options.normalize)?;
+            Some((TextLine { text, ..line }, (section, checkbox)))
+        })
+        .unzip();
+
+    let mut items: Vec<ListItem> = lines
+        .iter()
+        .zip(marks)
+        .map(|(line, (section, checkbox))| ListItem {
+            section,
+            checkbox,
+            ..parse_item(&line.text, &options.parser)
+        })
+        .collect();
+
+    if options.spelling.enabled {
+        let corrector = Corrector::new(&options.spelling, &context.history);
+        for item in items.iter_mut() {
+            if let Some(corrected) = corrector.correct(&item.content) {
+                item.original = Some(std::mem::replace(&mut item.content, corrected));
+            }
+        }
+    }
+    // before the shopping stage, so that "Milk" and "Milch" can be merged
+    translate::apply(&mut items, &options.translate);
+    nest_items(&lines, &mut items, &options.layout);
+    shopping::apply(&mut items, &options.shopping);
+    grocery::apply(&mut items, &options.grocery, context.shopping_list);
+    items
+}
@@ -0,0 +1,151 @@
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use zeroize::Zeroize;
+
+/// A credential (private key, token, password). Debug and Display print
+/// `***`, so it cannot end up in a log by accident, and the memory is
+/// wiped when it is dropped. Serde sees the plain value.
+#[derive(Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn new(value: impl Into<String>) -> Self {
+        Secret(value.into())
+    }
+
+    /// The value, only for the places that send it.
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Hands the value to an owner that wipes it as well (`HttpRequest`).
+    pub(crate) fn into_string(mut self) -> String {
+        std::mem::take(&mut self.0)
+    }
+}
+
+impl From<String> for Secret {
+    fn from(value: String) -> Self {
+        Secret(value)
+    }
+}
+
+impl From<&str> for Secret {
+    fn from(value: &str) -> Self {
+        Secret(value.to_string())
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("***")
+    }
+}
+
+impl fmt::Display for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("***")
+    }
+}
+
+impl Drop for Secret {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+/// Parses json that holds credentials. The errors of serde can quote the
+/// values of the json, these only tell what is missing or where the json breaks,
+/// as they end up in the console.
+pub(crate) fn from_credentials_json<T: DeserializeOwned>(json: &str) -> Result<T, String> {
+    serde_json::from_str(json).map_err(|e| {
+        let message = e.to_string();
+        if message.starts_with("missing field") {
+            message
+        } else {
+            format!(
+                "the credentials are not as expected (line {}, column {})",
+                e.line(),
+                e.column()
+            )
+        }
+    })
+}
+
+#[cfg(test)]
+mod test {
+
+    extern crate wasm_bindgen_test;
+
+    use wasm_bindgen_test::*;
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    use super::{from_credentials_json, Secret};
+    use crate::auth::Creds;
+    use crate::google_oauth::UserTokens;
+    use crate::http::HttpRequest;
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn secrets_are_not_printed() {
+        let secret = Secret::from("ya29.token");
+
+        assert_eq!("***", format!("{}", secret));
+        assert_eq!("***", format!("{:?}", secret));
+        assert_eq!("ya29.token", secret.expose());
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn serde_sees_the_value() {
+        let secret: Secret = serde_json::from_str(r#""ya29.token""#).unwrap();
+
+        assert_eq!(Secret::from("ya29.token"), secret);
+        assert_eq!(r#""ya29.token""#, serde_json::to_string(&secret).unwrap());
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn debug_dumps_of_credentials_hide_the_key() {
+        let creds: Creds =
+            serde_json::from_str(include_str!("../test-assets/service-account.json")).unwrap();
+
+        let dump = format!("{:?}", creds);
+
+        assert!(dump.contains("private_key: ***"));
+        assert!(!dump.contains("PRIVATE KEY"));
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn debug_dumps_of_requests_hide_credential_headers() {
+        let request = HttpRequest::new("POST", "https://example.org/token")
+            .header("Authorization", "Bearer ya29.token")
+            .header("ocp-apim-subscription-key", "azure-key")
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .body(b"client_secret=very-secret".to_vec());
+
+        let dump = format!("{:?}", request);
+
+        assert!(dump.contains(r#"("Authorization", "***")"#));
+        assert!(dump.contains("application/x-www-form-urlencoded"));
+        assert!(!dump.contains("ya29"));
+        assert!(!dump.contains("azure-key"));
+        assert!(!dump.contains("very-secret"));
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn parse_errors_do_not_quote_values() {
+        let error = from_credentials_json::<UserTokens>(
+            r#"{"access_token": "ya29.token", "expires_at": "ya29.token", "scope": ""}"#,
+        )
+        .expect_err("test fails: expires_at is no number");
+        assert!(!error.contains("ya29"));
+
+        let error =
+            from_credentials_json::<Creds>("{}").expect_err("test fails: fields are missing");
+        assert_eq!("missing field `type` at line 1 column 2", error);
+    }
+}
@@ -0,0 +1,482 @@
+use crate::checkbox::CheckboxState;
+use crate::parser::ListItem;
+use serde::{Deserialize, Serialize};
+
+/// A line of a shopping list split into what and how much,
+/// e.g. "500g Hack" or "Eier (10)".
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ShoppingItem {
+    pub name: String,
+    pub quantity: Option<f64>,
+    // canonical unit, `None` for plain counts ("2x Milch")
+    pub unit: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ShoppingConfig {
+    // off by default: "3 Mails schreiben" is no shopping item
+    pub enabled: bool,
+    pub render: QuantityRender,
+    // "Milch" and "2x Milch" become one task "3x Milch"
+    pub merge_duplicates: bool,
+}
+
+/// Where the quantity ends up in the todoist-task.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QuantityRender {
+    // "500 g Hack"
+    #[default]
+    Content,
+    // content "Hack", description "500 g"
+    Description,
+}
+
+// (spelling, canonical unit), german and english
+const UNITS: &[(&str, &str)] = &[
+    ("g", "g"),
+    ("gr", "g"),
+    ("gramm", "g"),
+    ("gram", "g"),
+    ("grams", "g"),
+    ("kg", "kg"),
+    ("kilo", "kg"),
+    ("kilogramm", "kg"),
+    ("kilogram", "kg"),
+    ("ml", "ml"),
+    ("cl", "cl"),
+    ("l", "l"),
+    ("liter", "l"),
+    ("litre", "l"),
+    ("liters", "l"),
+    ("litres", "l"),
+    ("lb", "lb"),
+    ("lbs", "lb"),
+    ("oz", "oz"),
+    ("pck", "pack"),
+    ("pkg", "pack"),
+    ("pack", "pack"),
+    ("packs", "pack"),
+    ("packung", "pack"),
+    ("packungen", "pack"),
+    ("packet", "pack"),
+    ("stk", "piece"),
+    ("stück", "piece"),
+    ("piece", "piece"),
+    ("pieces", "piece"),
+    ("pcs", "piece"),
+    ("dose", "can"),
+    ("dosen", "can"),
+    ("can", "can"),
+    ("cans", "can"),
+    ("flasche", "bottle"),
+    ("flaschen", "bottle"),
+    ("bottle", "bottle"),
+    ("bottles", "bottle"),
+    ("bund", "bunch"),
+    ("bunch", "bunch"),
+    ("glas", "jar"),
+    ("gläser", "jar"),
+    ("jar", "jar"),
+    ("jars", "jar"),
+    ("tüte", "bag"),
+    ("tüten", "bag"),
+    ("beutel", "bag"),
+    ("bag", "bag"),
+    ("bags", "bag"),
+    ("becher", "cup"),
+    ("cup", "cup"),
+    ("cups", "cup"),
+];
+
+const NUMBER_WORDS: &[(&str, f64)] = &[
+    ("ein", 1.0),
+    ("eine", 1.0),
+    ("einen", 1.0),
+    ("zwei", 2.0),
+    ("drei", 3.0),
+    ("vier", 4.0),
+    ("fünf", 5.0),
+    ("sechs", 6.0),
+    ("sieben", 7.0),
+    ("acht", 8.0),
+    ("neun", 9.0),
+    ("zehn", 10.0),
+    ("elf", 11.0),
+    ("zwölf", 12.0),
+    ("dutzend", 12.0),
+    ("halb", 0.5),
+    ("halbe", 0.5),
+    ("one", 1.0),
+    ("two", 2.0),
+    ("three", 3.0),
+    ("four", 4.0),
+    ("five", 5.0),
+    ("six", 6.0),
+    ("seven", 7.0),
+    ("eight", 8.0),
+    ("nine", 9.0),
+    ("ten", 10.0),
+    ("eleven", 11.0),
+    ("twelve", 12.0),
+    ("dozen", 12.0),
+    ("half", 0.5),
+];
+
+impl ShoppingItem {
+    /// Understands the quantity in front of ("2x Milch", "500 g Hack",
+    /// "zwei Dosen Mais") or behind the name ("Eier (10)", "Milch 2x").
+    pub fn parse(line: &str) -> Self {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        leading_amount(&words)
+            .or_else(|| trailing_amount(&words))
+            .unwrap_or_else(|| ShoppingItem {
+                name: words.join(" "),
+                quantity: None,
+                unit: None,
+            })
+    }
+
+    pub fn amount(&self) -> Option<String> {
+        let quantity = format_quantity(self.quantity?);
+        Some(match &self.unit {
+            Some(unit) => format!("{} {}", quantity, unit),
+            None => format!("{}x", quantity),
+        })
+    }
+
+    fn render(&self) -> String {
+        match self.amount() {
+            Some(amount) => format!("{} {}", amount, self.name),
+            None => self.name.clone(),
+        }
+    }
+}
+
+/// Parses the content of every item, merges duplicates if configured
+/// and writes the quantities back into content or description.
+pub fn apply(items: &mut Vec<ListItem>, config: &ShoppingConfig) {
+    if !config.enabled {
+        return;
+    }
+    let mut parsed: Vec<ShoppingItem> = items
+        .iter()
+        .map(|i| ShoppingItem::parse(&i.content))
+        .collect();
+    if config.merge_duplicates {
+        merge_duplicates(items, &mut parsed);
+    }
+
+    for (item, shopping) in items.iter_mut().zip(parsed) {
+        match config.render {
+            QuantityRender::Content => item.content = shopping.render(),
+            QuantityRender::Description => {
+                if let Some(amount) = shopping.amount() {
+                    // keep what an earlier stage put there (e.g. the untranslated text)
+                    item.description = Some(match item.description.take() {
+                        Some(description) => format!("{}\n{}", amount, description),
+                        None => amount,
+                    });
+                }
+                item.content = shopping.name;
+            }
+        }
+    }
+}
+
+// Items that are parents of other items are left alone,
+// merging them would tear their subtasks apart. A ticked item is only
+// merged with ticked ones, what is bought does not add to what is still needed.
+fn merge_duplicates(items: &mut Vec<ListItem>, parsed: &mut Vec<ShoppingItem>) {
+    let is_parent =
+        |index: usize, items: &[ListItem]| items.iter().any(|i| i.parent == Some(index));
+    let is_ticked = |item: &ListItem| item.checkbox == Some(CheckboxState::Checked);
+
+    let mut removed = vec![false; items.len()];
+    for later in 0..items.len() {
+        if is_parent(later, items) {
+            continue;
+        }
+        let earlier = (0..later).find(|&earlier| {
+            !removed[earlier]
+                && !is_parent(earlier, items)
+                && items[earlier].section == items[later].section
+                && is_ticked(&items[earlier]) == is_ticked(&items[later])
+                && parsed[earlier].unit == parsed[later].unit
+                && parsed[earlier].name.to_lowercase() == parsed[later].name.to_lowercase()
+        });
+        if let Some(earlier) = earlier {
+            // a line without a quantity still means one of it
+            let quantity =
+                parsed[earlier].quantity.unwrap_or(1.0) + parsed[later].quantity.unwrap_or(1.0);
+            parsed[earlier].quantity = Some(quantity);
+            let later_item = items[later].clone();
+            let earlier_item = &mut items[earlier];
+            for label in later_item.labels {
+                if !earlier_item.labels.contains(&label) {
+                    earlier_item.labels.push(label);
+                }
+            }
+            earlier_item.priority = earlier_item.priority.max(later_item.priority);
+            removed[later] = true;
+        }
+    }
+
+    // shift the parent indices over the removed items
+    let mut new_index = Vec::with_capacity(items.len());
+    let mut next = 0;
+    for &gone in &removed {
+        new_index.push(next);
+        if !gone {
+            next += 1;
+        }
+    }
+    let mut index = 0;
+    items.retain(|_| {
+        index += 1;
+        !removed[index - 1]
+    });
+    let mut index = 0;
+    parsed.retain(|_| {
+        index += 1;
+        !removed[index - 1]
+    });
+    for item in items.iter_mut() {
+        item.parent = item.parent.map(|p| new_index[p]);
+    }
+}
+
+fn leading_amount(words: &[&str]) -> Option<ShoppingItem> {
+    let (quantity, mut unit) = parse_amount_word(words.first()?)?;
+    let mut used = 1;
+    if unit.is_none() {
+        if let Some(next) = words.get(1).and_then(|w| parse_unit(w)) {
+            unit = next;
+            used += 1;
+        }
+    }
+    let name = words[used..].join(" ");
+    (!name.is_empty()).then_some(ShoppingItem {
+        name,
+        quantity: Some(quantity),
+        unit,
+    })
+}
+
+fn trailing_amount(words: &[&str]) -> Option<ShoppingItem> {
+    let joined = words.join(" ");
+
+    // "Eier (10)", "Eier (10 Stück)"
+    if joined.ends_with(')') {
+        let open = joined.rfind('(')?;
+        let inner: Vec<&str> = joined[open + 1..joined.len() - 1]
+            .split_whitespace()
+            .collect();
+        let amount = leading_amount(&[&inner[..], &["_"]].concat())?;
+        if amount.name != "_" {
+            return None;
+        }
+        let name = joined[..open].trim_end().to_string();
+        return (!name.is_empty()).then_some(ShoppingItem { name, ..amount });
+    }
+
+    // "Milch 2x", "Hack 500g", "Hack 500 g"
+    for used in [2, 1] {
+        if words.len() <= used {
+            continue;
+        }
+        let (name, tail) = words.split_at(words.len() - used);
+        if let Some(amount) = leading_amount(&[tail, &["_"]].concat()) {
+            if amount.name == "_" {
+                return Some(ShoppingItem {
+                    name: name.join(" "),
+                    ..amount
+                });
+            }
+        }
+    }
+    None
+}
+
+// "2", "2x", "x2", "500g", "1,5kg", "zwei"
+// The outer option tells whether the word is an amount at all,
+// the inner one whether it already carries a unit.
+fn parse_amount_word(word: &str) -> Option<(f64, Option<String>)> {
+    let lower = word.to_lowercase();
+    if let Some(&(_, quantity)) = NUMBER_WORDS.iter().find(|(w, _)| *w == lower) {
+        return Some((quantity, None));
+    }
+    if let Some(count) = lower.strip_prefix(['x', '×']) {
+        return parse_number(count).map(|q| (q, None));
+    }
+
+    let number_end = lower
+        .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == ','))
+        .unwrap_or(lower.len());
+    let quantity = parse_number(&lower[..number_end])?;
+    match &lower[number_end..] {
+        "" | "x" | "×" => Some((quantity, None)),
+        suffix => parse_unit(suffix).map(|unit| (quantity, unit)),
+    }
+}
+
+// The outer option tells whether the word is a unit at all,
+// "x" is one but means "times" and carries no unit.
+fn parse_unit(word: &str) -> Option<Option<String>> {
+    let lower = word.to_lowercase();
+    let lower = lower.trim_end_matches('.');
+    if lower == "x" || lower == "×" {
+        return Some(None);
+    }
+    UNITS
+        .iter()
+        .find(|(spelling, _)| *spelling == lower)
+        .map(|(_, canonical)| Some(canonical.to_string()))
+}
+
+fn parse_number(number: &str) -> Option<f64> {
+    if number.is_empty() || !number.starts_with(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+    number.replace(',', ".").parse().ok()
+}
+
+fn format_quantity(quantity: f64) -> String {
+    if quantity.fract() == 0.0 {
+        format!("{}", quantity as i64)
+    } else {
+        format!("{}", quantity)
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    extern crate wasm_bindgen_test;
+
+    use wasm_bindgen_test::*;
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    use super::{apply, QuantityRender, ShoppingConfig, ShoppingItem};
+    use crate::checkbox::CheckboxState;
+    use crate::parser::ListItem;
+
+    fn shopping(name: &str, quantity: Option<f64>, unit: Option<&str>) -> ShoppingItem {
+        ShoppingItem {
+            name: name.to_string(),
+            quantity,
+            unit: unit.map(str::to_string),
+        }
+    }
+
+    fn items(contents: &[&str]) -> Vec<ListItem> {
+        contents
+            .iter()
+            .map(|c| ListItem {
+                content: c.to_string(),
+                ..ListItem::default()
+            })
+            .collect()
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn leading_quantities_are_understood() {
+        assert_eq!(
+            shopping("Milch", Some(2.0), None),
+            ShoppingItem::parse("2x Milch")
+        );
+        assert_eq!(
+            shopping("Hack", Some(500.0), Some("g")),
+            ShoppingItem::parse("500g Hack")
+        );
+        assert_eq!(
+            shopping("Äpfel", Some(1.5), Some("kg")),
+            ShoppingItem::parse("1,5 Kilo Äpfel")
+        );
+        assert_eq!(
+            shopping("Mais", Some(2.0), Some("can")),
+            ShoppingItem::parse("zwei Dosen Mais")
+        );
+        assert_eq!(
+            shopping("milk", Some(1.0), Some("l")),
+            ShoppingItem::parse("one litre milk")
+        );
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn trailing_quantities_are_understood() {
+        assert_eq!(
+            shopping("Eier", Some(10.0), None),
+            ShoppingItem::parse("Eier (10)")
+        );
+        assert_eq!(
+            shopping("Eier", Some(10.0), Some("piece")),
+            ShoppingItem::parse("Eier (10 Stück)")
+        );
+        assert_eq!(
+            shopping("Milch", Some(2.0), None),
+            ShoppingItem::parse("Milch 2x")
+        );
+        assert_eq!(
+            shopping("Butter", Some(2.0), Some("pack")),
+            ShoppingItem::parse("Butter 2 Pck.")
+        );
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn plain_names_have_no_quantity() {
+        assert_eq!(shopping("Brot", None, None), ShoppingItem::parse("Brot"));
+        assert_eq!(shopping("7Up", None, None), ShoppingItem::parse("7Up"));
+        assert_eq!(shopping("(10)", None, None), ShoppingItem::parse("(10)"));
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn quantities_can_move_to_description() {
+        let mut list = items(&["500g Hack", "Brot"]);
+        let config = ShoppingConfig {
+            enabled: true,
+            render: QuantityRender::Description,
+            ..ShoppingConfig::default()
+        };
+        apply(&mut list, &config);
+
+        assert_eq!("Hack", list[0].content);
+        assert_eq!(Some("500 g".to_string()), list[0].description);
+        assert_eq!("Brot", list[1].content);
+        assert_eq!(None, list[1].description);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn duplicates_are_merged() {
+        let mut list = items(&["Milch", "Eier (10)", "2x milch", "Party", "Chips"]);
+        list[4].parent = Some(3);
+        let config = ShoppingConfig {
+            enabled: true,
+            merge_duplicates: true,
+            ..ShoppingConfig::default()
+        };
+        apply(&mut list, &config);
+
+        let contents: Vec<_> = list.iter().map(|i| i.content.as_str()).collect();
+        assert_eq!(vec!["3x Milch", "10x Eier", "Party", "Chips"], contents);
+        assert_eq!(Some(2), list[3].parent);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn ticked_items_are_not_merged_with_open_ones() {
+        let mut list = items(&["Milch", "2x Milch", "Milch"]);
+        list[0].checkbox = Some(CheckboxState::Checked);
+        list[2].checkbox = Some(CheckboxState::Unchecked);
+        let config = ShoppingConfig {
+            enabled: true,
+            merge_duplicates: true,
+            ..ShoppingConfig::default()
+        };
+        apply(&mut list, &config);
+
+        let contents: Vec<_> = list.iter().map(|i| i.content.as_str()).collect();
+        assert_eq!(vec!["Milch", "3x Milch"], contents);
+        assert_eq!(Some(CheckboxState::Checked), list[0].checkbox);
+    }
+}
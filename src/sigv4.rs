@@ -0,0 +1,204 @@
+use crate::http::HttpRequest;
+use crate::secret::Secret;
+use chrono::{DateTime, Utc};
+use hmac_sha256::{Hash, HMAC};
+use serde::Deserialize;
+use zeroize::Zeroize;
+
+const ALGORITHM: &str = "AWS4-HMAC-SHA256";
+
+/// An aws access key, passed in as credentials json.
+#[derive(Debug, Clone, Deserialize)]
+pub struct AwsCredentials {
+    pub access_key_id: String,
+    pub secret_access_key: Secret,
+    // only for temporary credentials (e.g. from sts)
+    pub session_token: Option<Secret>,
+    pub region: String,
+}
+
+/// Signs the request with aws signature version 4 by adding the
+/// `host`, `x-amz-date` and `authorization` headers.
+/// `now` is passed in, so that signatures can be reproduced in tests.
+pub fn sign(
+    request: &mut HttpRequest,
+    credentials: &AwsCredentials,
+    service: &str,
+    now: DateTime<Utc>,
+) {
+    let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+    let date = now.format("%Y%m%d").to_string();
+    let (host, path, query) = split_url(&request.url);
+
+    let host = host.to_string();
+    request.headers.push(("host".to_string(), host));
+    request
+        .headers
+        .push(("x-amz-date".to_string(), amz_date.clone()));
+    if let Some(token) = &credentials.session_token {
+        request.headers.push((
+            "x-amz-security-token".to_string(),
+            token.clone().into_string(),
+        ));
+    }
+
+    let mut headers: Vec<(String, String)> = request
+        .headers
+        .iter()
+        .map(|(name, value)| (name.to_lowercase(), canonical_value(value)))
+        .collect();
+    headers.sort();
+    // these hold the session token, the headers of the request are wiped with it
+    let canonical_headers = Secret::new(
+        headers
+            .iter()
+            .map(|(name, value)| format!("{}:{}\n", name, value))
+            .collect::<String>(),
+    );
+    let signed_headers = headers
+        .iter()
+        .map(|(name, _)| name.as_str())
+        .collect::<Vec<_>>()
+        .join(";");
+
+    let canonical_request = [
+        request.method.as_str(),
+        &path,
+        &canonical_query(query),
+        canonical_headers.expose(),
+        &signed_headers,
+        &hex(&Hash::hash(&request.body)),
+    ]
+    .join("\n");
+    let canonical_request = Secret::new(canonical_request);
+    headers.iter_mut().for_each(|(_, value)| value.zeroize());
+
+    let scope = format!("{}/{}/{}/aws4_request", date, credentials.region, service);
+    let string_to_sign = [
+        ALGORITHM,
+        &amz_date,
+        &scope,
+        &hex(&Hash::hash(canonical_request.expose().as_bytes())),
+    ]
+    .join("\n");
+
+    let key = signing_key(
+        credentials.secret_access_key.expose(),
+        &date,
+        &credentials.region,
+        service,
+    );
+    let signature = hex(&HMAC::mac(string_to_sign.as_bytes(), &key));
+
+    request.headers.push((
+        "authorization".to_string(),
+        format!(
+            "{} Credential={}/{}, SignedHeaders={}, Signature={}",
+            ALGORITHM, credentials.access_key_id, scope, signed_headers, signature
+        ),
+    ));
+}
+
+fn signing_key(secret: &str, date: &str, region: &str, service: &str) -> [u8; 32] {
+    let k_secret = Secret::new(format!("AWS4{}", secret));
+    let k_date = HMAC::mac(date.as_bytes(), k_secret.expose().as_bytes());
+    let k_region = HMAC::mac(region.as_bytes(), &k_date);
+    let k_service = HMAC::mac(service.as_bytes(), &k_region);
+    HMAC::mac(b"aws4_request", &k_service)
+}
+
+// "https://textract.eu-central-1.amazonaws.com/?a=b" -> (host, "/", "a=b")
+fn split_url(url: &str) -> (&str, String, &str) {
+    let without_scheme = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let (host, path_and_query) = match without_scheme.find('/') {
+        Some(i) => without_scheme.split_at(i),
+        None => (without_scheme, "/"),
+    };
+    let (path, query) = path_and_query
+        .split_once('?')
+        .unwrap_or((path_and_query, ""));
+    (host, path.to_string(), query)
+}
+
+// the parameters sorted by name, they are expected to be encoded already
+fn canonical_query(query: &str) -> String {
+    let mut pairs: Vec<(&str, &str)> = query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| pair.split_once('=').unwrap_or((pair, "")))
+        .collect();
+    pairs.sort();
+    pairs
+        .iter()
+        .map(|(name, value)| format!("{}={}", name, value))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+// trimmed, and inner runs of whitespace collapse to one space
+fn canonical_value(value: &str) -> String {
+    value.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod test {
+
+    extern crate wasm_bindgen_test;
+
+    use wasm_bindgen_test::*;
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    use super::{hex, sign, signing_key, AwsCredentials};
+    use crate::http::HttpRequest;
+    use chrono::{TimeZone, Utc};
+
+    // the example credentials of the aws documentation
+    fn credentials() -> AwsCredentials {
+        AwsCredentials {
+            access_key_id: "AKIDEXAMPLE".to_string(),
+            secret_access_key: "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY".into(),
+            session_token: None,
+            region: "us-east-1".to_string(),
+        }
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn signing_key_matches_the_aws_example() {
+        let key = signing_key(
+            "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY",
+            "20120215",
+            "us-east-1",
+            "iam",
+        );
+        assert_eq!(
+            "f4780e2d9f65fa895f9c67b32ce1baf0b0d8a43505a000a1a9e090d414db404d",
+            hex(&key)
+        );
+    }
+
+    // "get-vanilla" of the aws signature v4 test suite
+    #[wasm_bindgen_test(unsupported = test)]
+    fn signs_the_vanilla_request() {
+        let mut request = HttpRequest::new("GET", "https://example.amazonaws.com/");
+        let now = Utc.with_ymd_and_hms(2015, 8, 30, 12, 36, 0).unwrap();
+
+        sign(&mut request, &credentials(), "service", now);
+
+        let authorization = request
+            .headers
+            .iter()
+            .find(|(name, _)| name == "authorization")
+            .map(|(_, value)| value.as_str());
+        assert_eq!(
+            Some("AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/service/aws4_request, SignedHeaders=host;x-amz-date, Signature=5fa00fa31553b73ebf1942676e86291e8372ff2a2260956d9b8aae1d763fbf31"),
+            authorization
+        );
+        assert!(request
+            .headers
+            .contains(&("x-amz-date".to_string(), "20150830T123600Z".to_string())));
+    }
+}
@@ -0,0 +1,189 @@
+use crate::checkbox::{CheckboxState, CheckedHandling};
+use crate::options::Options;
+use crate::parser::ListItem;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+/// A project (todoist), list (to do, google tasks) or calendar (caldav).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SinkProject {
+    pub id: String,
+    pub name: String,
+}
+
+/// A task that already exists in a project.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SinkTask {
+    pub id: String,
+    pub content: String,
+}
+
+/// What `upload` asks a sink to create.
+#[derive(Debug)]
+pub struct TaskDraft<'a> {
+    pub item: &'a ListItem,
+    // id of the already created parent task
+    pub parent_id: Option<String>,
+    // ticked on paper and `CheckedHandling::Complete`
+    pub completed: bool,
+}
+
+/// Somewhere the recognized items end up as tasks.
+#[async_trait(?Send)]
+pub trait TaskSink {
+    async fn list_projects(&self) -> Result<Vec<SinkProject>, String>;
+
+    /// Returns the id of the project with that name (ignoring case),
+    /// the project is created if there is none.
+    async fn ensure_project(&self, name: &str) -> Result<String, String>;
+
+    /// Returns the id of the created task.
+    async fn create_task(&self, project_id: &str, draft: &TaskDraft) -> Result<String, String>;
+
+    async fn find_tasks(&self, project_id: &str) -> Result<Vec<SinkTask>, String>;
+
+    /// Called once before the tasks are created, e.g. to create the
+    /// sections and labels the items refer to.
+    async fn prepare(&self, _project_id: &str, _items: &mut [ListItem], _options: &Options) {}
+}
+
+/// Creates a task for every item. Parents always come before their
+/// children, so their ids are known (skipped items have none, their
+/// children end up on the top level).
+/// Returns the id of every item's task.
+pub async fn upload(
+    sink: &dyn TaskSink,
+    project_id: &str,
+    mut items: Vec<ListItem>,
+    options: &Options,
+) -> Result<Vec<Option<String>>, String> {
+    sink.prepare(project_id, &mut items, options).await;
+
+    let mut created_ids: Vec<Option<String>> = Vec::with_capacity(items.len());
+    for item in &items {
+        if is_skipped(item, options) {
+            created_ids.push(None);
+            continue;
+        }
+        let checked = item.checkbox == Some(CheckboxState::Checked);
+        let draft = TaskDraft {
+            item,
+            parent_id: item
+                .parent
+                .and_then(|p| created_ids.get(p).cloned().flatten()),
+            completed: checked && options.checkbox.checked == CheckedHandling::Complete,
+        };
+        let id = sink.create_task(project_id, &draft).await?;
+        created_ids.push(Some(id));
+    }
+    Ok(created_ids)
+}
+
+/// Items ticked on paper that `upload` leaves out.
+pub(crate) fn is_skipped(item: &ListItem, options: &Options) -> bool {
+    item.checkbox == Some(CheckboxState::Checked)
+        && options.checkbox.checked == CheckedHandling::Skip
+}
+
+#[cfg(test)]
+mod test {
+
+    extern crate wasm_bindgen_test;
+
+    use wasm_bindgen_test::*;
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    use super::{upload, SinkProject, SinkTask, TaskDraft, TaskSink};
+    use crate::checkbox::{CheckboxState, CheckedHandling};
+    use crate::options::Options;
+    use crate::parser::ListItem;
+    use async_trait::async_trait;
+    use futures::executor::block_on;
+    use std::cell::RefCell;
+
+    // remembers (content, parent_id, completed) of every created task
+    #[derive(Default)]
+    struct Recorder(RefCell<Vec<(String, Option<String>, bool)>>);
+
+    #[async_trait(?Send)]
+    impl TaskSink for Recorder {
+        async fn list_projects(&self) -> Result<Vec<SinkProject>, String> {
+            Ok(Vec::new())
+        }
+
+        async fn ensure_project(&self, name: &str) -> Result<String, String> {
+            Ok(name.to_string())
+        }
+
+        async fn create_task(
+            &self,
+            _project_id: &str,
+            draft: &TaskDraft,
+        ) -> Result<String, String> {
+            let mut created = self.0.borrow_mut();
+            created.push((
+                draft.item.content.clone(),
+                draft.parent_id.clone(),
+                draft.completed,
+            ));
+            Ok(format!("task-{}", created.len()))
+        }
+
+        async fn find_tasks(&self, _project_id: &str) -> Result<Vec<SinkTask>, String> {
+            Ok(Vec::new())
+        }
+    }
+
+    fn item(content: &str, parent: Option<usize>, checkbox: Option<CheckboxState>) -> ListItem {
+        ListItem {
+            content: content.to_string(),
+            parent,
+            checkbox,
+            ..ListItem::default()
+        }
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn children_point_to_their_parent_task() {
+        let sink = Recorder::default();
+        let items = vec![
+            item("Party", None, None),
+            item("Chips", Some(0), None),
+            item("Cola", Some(0), Some(CheckboxState::Checked)),
+            item("Brot", None, Some(CheckboxState::Unchecked)),
+        ];
+
+        let ids = block_on(upload(&sink, "list", items, &Options::default()))
+            .expect("test fails: recorder never fails");
+
+        assert_eq!(
+            vec![
+                Some("task-1".to_string()),
+                Some("task-2".to_string()),
+                None,
+                Some("task-3".to_string())
+            ],
+            ids
+        );
+        let created = sink.0.borrow();
+        assert_eq!(Some("task-1".to_string()), created[1].1);
+        assert_eq!(None, created[2].1);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn checked_items_can_be_completed() {
+        let sink = Recorder::default();
+        let mut options = Options::default();
+        options.checkbox.checked = CheckedHandling::Complete;
+
+        block_on(upload(
+            &sink,
+            "list",
+            vec![item("Milch", None, Some(CheckboxState::Checked))],
+            &options,
+        ))
+        .expect("test fails: recorder never fails");
+
+        assert!(sink.0.borrow()[0].2);
+    }
+}
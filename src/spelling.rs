@@ -0,0 +1,168 @@
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct SpellingConfig {
+    pub enabled: bool,
+    // words (or whole items) the user wants to be spelled this way
+    pub dictionary: Vec<String>,
+    // also learn from the tasks that are already in the target project
+    pub use_history: bool,
+    // 1.0 is a perfect match, "Tomoten" -> "Tomaten" is ~0.86
+    pub min_confidence: f32,
+    // shorter words are left alone, too many words are close to them
+    pub min_word_len: usize,
+}
+
+impl Default for SpellingConfig {
+    fn default() -> Self {
+        SpellingConfig {
+            enabled: false,
+            dictionary: Vec::new(),
+            use_history: true,
+            min_confidence: 0.8,
+            min_word_len: 4,
+        }
+    }
+}
+
+/// Corrects single words against a vocabulary of known words.
+pub struct Corrector<'a> {
+    vocabulary: Vec<String>,
+    config: &'a SpellingConfig,
+}
+
+impl<'a> Corrector<'a> {
+    /// Builds the vocabulary from the dictionary and the contents of
+    /// earlier tasks. Both can hold whole phrases, they are split into words.
+    pub fn new(config: &'a SpellingConfig, history: &[String]) -> Self {
+        let mut vocabulary: Vec<String> = Vec::new();
+        for entry in config.dictionary.iter().chain(history) {
+            for word in entry.split_whitespace() {
+                let word = word.trim_matches(|c: char| !c.is_alphanumeric());
+                if !word.is_empty() && !vocabulary.iter().any(|v| v.eq_ignore_ascii_case(word)) {
+                    vocabulary.push(word.to_string());
+                }
+            }
+        }
+        Corrector { vocabulary, config }
+    }
+
+    /// Returns the corrected text, or `None` if nothing was changed.
+    pub fn correct(&self, text: &str) -> Option<String> {
+        let mut changed = false;
+        let corrected: Vec<String> = text
+            .split(' ')
+            .map(|word| match self.correct_word(word) {
+                Some(correction) => {
+                    changed = true;
+                    correction
+                }
+                None => word.to_string(),
+            })
+            .collect();
+        changed.then(|| corrected.join(" "))
+    }
+
+    // Only words made of letters are corrected, and only if there is
+    // exactly one candidate that is close enough.
+    fn correct_word(&self, word: &str) -> Option<String> {
+        if word.chars().count() < self.config.min_word_len || !word.chars().all(char::is_alphabetic)
+        {
+            return None;
+        }
+        let lower = word.to_lowercase();
+        if self.vocabulary.iter().any(|v| v.to_lowercase() == lower) {
+            return None;
+        }
+
+        let mut candidates = self.vocabulary.iter().filter(|known| {
+            let known_lower = known.to_lowercase();
+            let longest = known_lower.chars().count().max(lower.chars().count());
+            let distance = edit_distance(&lower, &known_lower);
+            1.0 - distance as f32 / longest as f32 >= self.config.min_confidence
+        });
+        match (candidates.next(), candidates.next()) {
+            (Some(candidate), None) => Some(candidate.clone()),
+            _ => None,
+        }
+    }
+}
+
+/// Levenshtein distance over chars (not bytes, "ü" counts as one).
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+
+    for (i, ca) in a.chars().enumerate() {
+        current[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod test {
+
+    extern crate wasm_bindgen_test;
+
+    use wasm_bindgen_test::*;
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    use super::{edit_distance, Corrector, SpellingConfig};
+
+    fn config(dictionary: &[&str]) -> SpellingConfig {
+        SpellingConfig {
+            enabled: true,
+            dictionary: dictionary.iter().map(|w| w.to_string()).collect(),
+            ..SpellingConfig::default()
+        }
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn distance_counts_chars() {
+        assert_eq!(0, edit_distance("Milch", "Milch"));
+        assert_eq!(1, edit_distance("Tomoten", "Tomaten"));
+        assert_eq!(1, edit_distance("Jogurt", "Joghurt"));
+        assert_eq!(1, edit_distance("Müsli", "Musli"));
+        assert_eq!(3, edit_distance("", "abc"));
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn close_words_are_corrected() {
+        let config = config(&["Tomaten", "Joghurt"]);
+        let corrector = Corrector::new(&config, &[]);
+        assert_eq!(
+            Some("2 Tomaten".to_string()),
+            corrector.correct("2 Tomoten")
+        );
+        assert_eq!(Some("Joghurt".to_string()), corrector.correct("Jogurt"));
+        assert_eq!(None, corrector.correct("Tomaten"));
+        assert_eq!(None, corrector.correct("Brot"));
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn history_counts_as_vocabulary() {
+        let config = config(&[]);
+        let history = vec!["Katzenfutter kaufen".to_string()];
+        let corrector = Corrector::new(&config, &history);
+        assert_eq!(
+            Some("Katzenfutter".to_string()),
+            corrector.correct("Katzenfuter")
+        );
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn ambiguous_or_short_words_stay() {
+        let config = config(&["Hasen", "Rasen", "Eis"]);
+        let corrector = Corrector::new(&config, &[]);
+        // "Hasen" and "Rasen" are both one edit away from "Basen"
+        assert_eq!(None, corrector.correct("Basen"));
+        assert_eq!(None, corrector.correct("Eiz"));
+    }
+}
@@ -0,0 +1,158 @@
+use crate::layout::{TextLine, WordBox};
+use crate::ocr::{Document, OcrEngine};
+use async_trait::async_trait;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Runs a locally installed tesseract, the image never leaves the machine.
+pub struct Tesseract {
+    // path to the tesseract executable
+    pub command: String,
+    // tesseract language codes, e.g. "deu+eng"
+    pub languages: String,
+}
+
+impl Default for Tesseract {
+    fn default() -> Self {
+        Tesseract {
+            command: "tesseract".to_string(),
+            languages: "deu+eng".to_string(),
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl OcrEngine for Tesseract {
+    async fn recognize(&self, img_data: &str) -> Result<Document, String> {
+        let image = base64::decode(img_data).map_err(|e| e.to_string())?;
+
+        let mut child = Command::new(&self.command)
+            .args(["stdin", "stdout", "-l", &self.languages, "tsv"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("Could not start {}: {}", self.command, e))?;
+        child
+            .stdin
+            .take()
+            .ok_or("tesseract has no stdin")?
+            .write_all(&image)
+            .map_err(|e| e.to_string())?;
+        let output = child.wait_with_output().map_err(|e| e.to_string())?;
+        if !output.status.success() {
+            return Err(String::from_utf8_lossy(&output.stderr).into_owned());
+        }
+        parse_tsv(&String::from_utf8_lossy(&output.stdout))
+    }
+}
+
+// the columns of tesseract's tsv output
+const LEVEL: usize = 0;
+// page, block, paragraph and line number identify a line
+const PAGE: usize = 1;
+const LINE: usize = 4;
+const LEFT: usize = 6;
+const TOP: usize = 7;
+const WIDTH: usize = 8;
+const HEIGHT: usize = 9;
+const CONFIDENCE: usize = 10;
+const TEXT: usize = 11;
+const WORD_LEVEL: &str = "5";
+
+/// Groups the words of the tsv (one row per word, after a header row)
+/// into the lines they were found in.
+fn parse_tsv(tsv: &str) -> Result<Document, String> {
+    let mut lines: Vec<(&[&str], Vec<WordBox>)> = Vec::new();
+    let rows: Vec<Vec<&str>> = tsv
+        .lines()
+        .skip(1)
+        .map(|row| row.split('\t').collect())
+        .collect();
+
+    for row in &rows {
+        if row.len() <= TEXT || row[LEVEL] != WORD_LEVEL || row[TEXT].trim().is_empty() {
+            continue;
+        }
+        let number = |column: usize| {
+            row[column]
+                .parse::<u32>()
+                .map_err(|e| format!("column {} of {:?}: {}", column, row, e))
+        };
+        let confidence = row[CONFIDENCE]
+            .parse::<f32>()
+            .map_err(|e| format!("confidence of {:?}: {}", row, e))?;
+        let word = WordBox {
+            text: row[TEXT].trim().to_string(),
+            left: number(LEFT)?,
+            top: number(TOP)?,
+            width: number(WIDTH)?,
+            height: number(HEIGHT)?,
+            // tesseract reports 0 to 100, -1 if there is none
+            confidence: (confidence >= 0.0).then_some(confidence / 100.0),
+        };
+
+        let key = &row[PAGE..=LINE];
+        match lines.last_mut() {
+            Some((last_key, words)) if *last_key == key => words.push(word),
+            _ => lines.push((key, vec![word])),
+        }
+    }
+
+    let lines: Vec<TextLine> = lines
+        .into_iter()
+        .map(|(_, words)| {
+            let text = words
+                .iter()
+                .map(|w| w.text.as_str())
+                .collect::<Vec<_>>()
+                .join(" ");
+            TextLine::from_words(&text, words)
+        })
+        .collect();
+    let text = lines
+        .iter()
+        .map(|l| l.text.as_str())
+        .collect::<Vec<_>>()
+        .join("\n");
+    Ok(Document { text, lines })
+}
+
+#[cfg(test)]
+mod test {
+
+    extern crate wasm_bindgen_test;
+
+    use wasm_bindgen_test::*;
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    use super::parse_tsv;
+
+    const TSV: &str = "level\tpage_num\tblock_num\tpar_num\tline_num\tword_num\tleft\ttop\twidth\theight\tconf\ttext
+1\t1\t0\t0\t0\t0\t0\t0\t800\t600\t-1\t
+4\t1\t1\t1\t1\t0\t10\t5\t120\t30\t-1\t
+5\t1\t1\t1\t1\t1\t10\t5\t60\t30\t96.5\tParty:
+4\t1\t1\t1\t2\t0\t40\t40\t120\t24\t-1\t
+5\t1\t1\t1\t2\t1\t40\t40\t20\t24\t91\t2
+5\t1\t1\t1\t2\t2\t70\t42\t90\t22\t81\tChips
+5\t1\t1\t1\t2\t3\t170\t42\t10\t22\t-1\t
+";
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn words_are_grouped_into_lines() {
+        let document = parse_tsv(TSV).expect("test fails: tsv must parse");
+
+        assert_eq!("Party:\n2 Chips", document.text);
+        assert_eq!(2, document.lines.len());
+        let chips = &document.lines[1];
+        assert_eq!("2 Chips", chips.text);
+        assert_eq!((40, 40, 24), (chips.left, chips.top, chips.height));
+        assert_eq!(Some(0.86), chips.confidence());
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn broken_rows_are_an_error() {
+        let tsv = "header\n5\t1\t1\t1\t1\t1\tten\t5\t60\t30\t96\tMilch";
+        assert!(parse_tsv(tsv).is_err());
+    }
+}
@@ -0,0 +1,286 @@
+use crate::http::{DefaultClient, HttpClient, HttpRequest, HttpResponse};
+use crate::layout::{TextLine, WordBox};
+use crate::ocr::{Document, OcrEngine};
+use crate::secret;
+use crate::sigv4::{self, AwsCredentials};
+use crate::types::textract as tt;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::convert::TryInto;
+
+const SERVICE: &str = "textract";
+const TARGET: &str = "Textract.DetectDocumentText";
+// textract answers with boxes relative to the page,
+// used if the size of the image cannot be read from its header
+const FALLBACK_SIZE: (u32, u32) = (10_000, 10_000);
+
+/// AWS Textract (DetectDocumentText), signed with an aws access key.
+pub struct Textract<C> {
+    credentials: AwsCredentials,
+    client: C,
+    // the time the requests are signed with
+    pub clock: fn() -> DateTime<Utc>,
+}
+
+impl Textract<DefaultClient> {
+    pub fn new(credentials_json: &str) -> Result<Self, String> {
+        let credentials = secret::from_credentials_json(credentials_json)?;
+        Ok(Textract::with_client(credentials, DefaultClient::default()))
+    }
+}
+
+impl<C: HttpClient> Textract<C> {
+    pub fn with_client(credentials: AwsCredentials, client: C) -> Self {
+        Textract {
+            credentials,
+            client,
+            clock: Utc::now,
+        }
+    }
+
+    fn request(&self, img_data: &str) -> Result<HttpRequest, String> {
+        let url = format!(
+            "https://textract.{}.amazonaws.com/",
+            self.credentials.region
+        );
+        let body = tt::DetectDocumentText {
+            document: tt::Document {
+                bytes: img_data.to_string(),
+            },
+        };
+        let mut request = HttpRequest::new("POST", &url)
+            .header("content-type", "application/x-amz-json-1.1")
+            .header("x-amz-target", TARGET)
+            .body(serde_json::to_vec(&body).map_err(|e| e.to_string())?);
+        sigv4::sign(&mut request, &self.credentials, SERVICE, (self.clock)());
+        Ok(request)
+    }
+}
+
+#[async_trait(?Send)]
+impl<C: HttpClient> OcrEngine for Textract<C> {
+    async fn recognize(&self, img_data: &str) -> Result<Document, String> {
+        let image = base64::decode(img_data).map_err(|e| e.to_string())?;
+        let response = self.client.send(self.request(img_data)?).await?;
+        if !response.is_success() {
+            return Err(api_error(&response));
+        }
+        let detected: tt::DetectDocumentTextResponse = response.json()?;
+        let size = image_size(&image).unwrap_or(FALLBACK_SIZE);
+        Ok(blocks_to_document(&detected.blocks, size))
+    }
+}
+
+fn api_error(response: &HttpResponse) -> String {
+    match response.json::<tt::ApiError>() {
+        Ok(e) => format!("{}: {}", e.kind, e.message),
+        Err(_) => format!("textract answered with status {}", response.status),
+    }
+}
+
+/// Lines come in reading order, their words are found through
+/// the CHILD relationship.
+fn blocks_to_document(blocks: &[tt::Block], size: (u32, u32)) -> Document {
+    let by_id: HashMap<&str, &tt::Block> = blocks.iter().map(|b| (b.id.as_str(), b)).collect();
+
+    let lines: Vec<TextLine> = blocks
+        .iter()
+        .filter(|b| b.block_type == "LINE")
+        .map(|line| {
+            let words = line
+                .relationships
+                .iter()
+                .filter(|r| r.r#type == "CHILD")
+                .flat_map(|r| &r.ids)
+                .filter_map(|id| by_id.get(id.as_str()))
+                .filter(|b| b.block_type == "WORD")
+                .map(|word| word_box(word, size))
+                .collect();
+            TextLine::from_words(line.text.as_deref().unwrap_or_default(), words)
+        })
+        .collect();
+    let text = lines
+        .iter()
+        .map(|l| l.text.as_str())
+        .collect::<Vec<_>>()
+        .join("\n");
+    Document { text, lines }
+}
+
+fn word_box(block: &tt::Block, (width, height): (u32, u32)) -> WordBox {
+    let scale = |relative: f64, total: u32| (relative.max(0.0) * f64::from(total)).round() as u32;
+    let (left, top, w, h) = match &block.geometry {
+        Some(geometry) => {
+            let b = &geometry.bounding_box;
+            (
+                scale(b.left, width),
+                scale(b.top, height),
+                scale(b.width, width),
+                scale(b.height, height),
+            )
+        }
+        None => (0, 0, 0, 0),
+    };
+    WordBox {
+        text: block.text.clone().unwrap_or_default(),
+        left,
+        top,
+        width: w,
+        height: h,
+        confidence: block.confidence.map(|c| c / 100.0),
+    }
+}
+
+// reads width and height from the header of a png or jpeg
+fn image_size(image: &[u8]) -> Option<(u32, u32)> {
+    let be_u32 = |at: usize| Some(u32::from_be_bytes(image.get(at..at + 4)?.try_into().ok()?));
+    let be_u16 = |at: usize| {
+        Some(u32::from(u16::from_be_bytes(
+            image.get(at..at + 2)?.try_into().ok()?,
+        )))
+    };
+
+    if image.starts_with(b"\x89PNG\r\n\x1a\n") {
+        return Some((be_u32(16)?, be_u32(20)?));
+    }
+    if !image.starts_with(&[0xFF, 0xD8]) {
+        return None;
+    }
+    // walk the jpeg segments up to the start of frame
+    let mut at = 2;
+    while at + 4 <= image.len() {
+        if image[at] != 0xFF {
+            return None;
+        }
+        let marker = image[at + 1];
+        let length = be_u16(at + 2)? as usize;
+        let start_of_frame =
+            (0xC0..=0xCF).contains(&marker) && ![0xC4, 0xC8, 0xCC].contains(&marker);
+        if start_of_frame {
+            return Some((be_u16(at + 7)?, be_u16(at + 5)?));
+        }
+        at += 2 + length;
+    }
+    None
+}
+
+#[cfg(test)]
+mod test {
+
+    extern crate wasm_bindgen_test;
+
+    use wasm_bindgen_test::*;
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    use super::{image_size, Textract};
+    use crate::http::{HttpResponse, Replay};
+    use crate::ocr::OcrEngine;
+    use crate::sigv4::AwsCredentials;
+    use chrono::{DateTime, TimeZone, Utc};
+    use futures::executor::block_on;
+
+    fn fixed_clock() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2023, 3, 11, 14, 2, 10).unwrap()
+    }
+
+    fn engine(response: HttpResponse) -> Textract<Replay> {
+        let credentials = AwsCredentials {
+            access_key_id: "AKIDEXAMPLE".to_string(),
+            secret_access_key: "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY".into(),
+            session_token: None,
+            region: "eu-central-1".to_string(),
+        };
+        let mut engine = Textract::with_client(credentials, Replay::new(vec![response]));
+        engine.clock = fixed_clock;
+        engine
+    }
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+        png.extend_from_slice(&width.to_be_bytes());
+        png.extend_from_slice(&height.to_be_bytes());
+        png
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn lines_and_words_are_mapped() {
+        let engine = engine(HttpResponse {
+            status: 200,
+            body: include_bytes!("../test-assets/textract-response.json").to_vec(),
+            ..HttpResponse::default()
+        });
+
+        let document = block_on(engine.recognize(&base64::encode(png(1000, 800))))
+            .expect("test fails: recorded response must give a document");
+
+        assert_eq!("Einkaufen:\n2 Milch", document.text);
+        let milch = &document.lines[1];
+        assert_eq!(
+            vec!["2", "Milch"],
+            milch
+                .words
+                .iter()
+                .map(|w| w.text.as_str())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!((60, 96, 40), (milch.left, milch.top, milch.height));
+        assert_eq!(Some(0.95), milch.words[1].confidence);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn requests_are_signed_with_the_clock() {
+        let engine = engine(HttpResponse {
+            status: 200,
+            body: br#"{"Blocks": []}"#.to_vec(),
+            ..HttpResponse::default()
+        });
+
+        block_on(engine.recognize(&base64::encode(png(10, 10))))
+            .expect("test fails: empty blocks are fine");
+
+        let requests = engine.client.requests.borrow();
+        let header = |name: &str| {
+            requests[0]
+                .headers
+                .iter()
+                .find(|(n, _)| n == name)
+                .map(|(_, value)| value.clone())
+                .unwrap_or_default()
+        };
+        assert_eq!(
+            "https://textract.eu-central-1.amazonaws.com/",
+            requests[0].url
+        );
+        assert_eq!("Textract.DetectDocumentText", header("x-amz-target"));
+        assert_eq!("20230311T140210Z", header("x-amz-date"));
+        assert!(header("authorization").starts_with(
+            "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20230311/eu-central-1/textract/aws4_request, SignedHeaders=content-type;host;x-amz-date;x-amz-target, Signature="
+        ));
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn api_errors_are_reported() {
+        let engine = engine(HttpResponse {
+            status: 400,
+            body: br#"{"__type": "InvalidSignatureException", "message": "Signature expired"}"#
+                .to_vec(),
+            ..HttpResponse::default()
+        });
+
+        let error = block_on(engine.recognize(&base64::encode(png(10, 10))))
+            .expect_err("test fails: a 400 must be an error");
+        assert_eq!("InvalidSignatureException: Signature expired", error);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn image_size_comes_from_the_header() {
+        assert_eq!(Some((1000, 800)), image_size(&png(1000, 800)));
+        let jpeg = [
+            0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x04, 0x00, 0x00, 0xFF, 0xC0, 0x00, 0x11, 0x08, 0x02,
+            0x58, 0x03, 0x20,
+        ];
+        assert_eq!(Some((800, 600)), image_size(&jpeg));
+        assert_eq!(None, image_size(b"GIF89a"));
+    }
+}
@@ -1,119 +1,442 @@
-use crate::types::todoist::{Project, ProjectResponse, Task, TaskResponse};
+use crate::http::{DefaultClient, HttpClient, HttpRequest};
+use crate::options::{LabelPolicy, Options};
+use crate::parser::ListItem;
+use crate::secret::Secret;
+use crate::sink::{self, SinkProject, SinkTask, TaskDraft, TaskSink};
+use crate::types::todoist::{
+    Label, LabelResponse, OpenTask, Project, ProjectResponse, Section, SectionResponse, Task,
+    TaskResponse,
+};
 use crate::utils;
-use utils::fetch;
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+use std::cell::RefCell;
 use wasm_bindgen::prelude::*;
-use wasm_bindgen::JsCast;
-use wasm_bindgen_futures::JsFuture;
-use web_sys::{self, Request, RequestInit, Response};
-
-const PROJECTS_URL: &str = "https://api.todoist.com/rest/v2/projects";
-const TASKS_URL: &str = "https://api.todoist.com/rest/v2/tasks";
-const SHOPPING_LIST: &str = "Einkaufsliste";
-
-pub(crate) async fn make_or_update_project<'a, I>(list_id: u32, items: I, token: &str) -> JsValue
-where
-    I: Iterator<Item = &'a str>,
-{
-    let id = if list_id == 0 {
-        create_shopping_list(token).await.unwrap()
-    } else {
-        list_id
-    };
 
-    for item in items {
-        utils::console_log("WASM - creating Task for item:", &item);
-        create_task(Task::new(&item, id as u64), token)
-            .await
-            .expect("Could not create item. sorryyyy");
+const TODOIST_URL: &str = "https://api.todoist.com/rest/v2";
+pub(crate) const SHOPPING_LIST: &str = "Einkaufsliste";
+
+/// The todoist rest api, authenticated with the api token of the user.
+pub struct Todoist<C> {
+    token: Secret,
+    client: C,
+    pub base_url: String,
+    // (heading, section_id) of the sections `prepare` found or created
+    section_ids: RefCell<Vec<(String, String)>>,
+}
+
+impl Todoist<DefaultClient> {
+    pub fn new(token: &str) -> Self {
+        Todoist::with_client(token, DefaultClient::default())
     }
-    JsValue::from(id)
 }
 
-async fn create_shopping_list(token: &str) -> Option<u32> {
-    let new_project = create_project(Project::new(SHOPPING_LIST), token)
-        .await
-        .expect("Could not create ShoppingList-Project");
-    Some(new_project.id as u32)
+impl<C: HttpClient> Todoist<C> {
+    pub fn with_client(token: &str, client: C) -> Self {
+        Todoist {
+            token: Secret::from(token),
+            client,
+            base_url: TODOIST_URL.to_string(),
+            section_ids: RefCell::new(Vec::new()),
+        }
+    }
+
+    fn request(&self, method: &str, path: &str) -> HttpRequest {
+        HttpRequest::new(method, &format!("{}{}", self.base_url, path)).bearer(&self.token)
+    }
+
+    async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T, String> {
+        self.client
+            .send(self.request("GET", path))
+            .await?
+            .into_json()
+    }
+
+    async fn post<T: DeserializeOwned, B: serde::Serialize>(
+        &self,
+        path: &str,
+        body: &B,
+    ) -> Result<T, String> {
+        let request = self
+            .request("POST", path)
+            .header("Content-Type", "application/json")
+            .header("X-Request-Id", &uuid::Uuid::new_v4().to_string())
+            .body(serde_json::to_vec(body).map_err(|e| e.to_string())?);
+        self.client.send(request).await?.into_json()
+    }
+
+    async fn close_task(&self, task_id: &str) -> Result<(), String> {
+        let request = self.request("POST", &format!("/tasks/{}/close", task_id));
+        let response = self.client.send(request).await?;
+        if response.is_success() {
+            Ok(())
+        } else {
+            Err(format!("status {}", response.status))
+        }
+    }
+
+    /// Reuses the sections of the project that are named like the headings
+    /// (ignoring case) and creates the missing ones.
+    /// Returns (heading, section_id) pairs.
+    async fn ensure_sections(
+        &self,
+        items: &[&ListItem],
+        project_id: &str,
+    ) -> Vec<(String, String)> {
+        let mut section_ids: Vec<(String, String)> = Vec::new();
+        if items.iter().all(|i| i.section.is_none()) {
+            return section_ids;
+        }
+        let existing: Vec<SectionResponse> = self
+            .get(&format!("/sections?project_id={}", project_id))
+            .await
+            .unwrap_or_default();
+
+        for name in items.iter().filter_map(|i| i.section.as_ref()) {
+            if section_ids.iter().any(|(n, _)| n == name) {
+                continue;
+            }
+            let section_id = match existing.iter().find(|s| s.name.eq_ignore_ascii_case(name)) {
+                Some(section) => Some(section.id.clone()),
+                None => self
+                    .post::<SectionResponse, _>("/sections", &Section::new(name, project_id))
+                    .await
+                    .map(|section| section.id)
+                    .ok(),
+            };
+            // items of a section that could not be created end up without one
+            if let Some(section_id) = section_id {
+                section_ids.push((name.clone(), section_id));
+            }
+        }
+        section_ids
+    }
+
+    /// Aligns the parsed label names with the labels of the account.
+    /// Existing labels are matched case-insensitively and keep their spelling.
+    /// If the labels cannot be read, `AutoCreate` sends the parsed names as
+    /// they are (todoist creates them with the task) and `AllowList` drops them.
+    async fn resolve_labels(&self, items: &mut [ListItem], policy: LabelPolicy) {
+        let mut known: Vec<String> = match self.get::<Vec<LabelResponse>>("/labels").await {
+            Ok(labels) => labels.into_iter().map(|label| label.name).collect(),
+            Err(_) => {
+                if policy == LabelPolicy::AllowList {
+                    // nothing can be checked against the allow-list
+                    items.iter_mut().for_each(|item| item.labels.clear());
+                }
+                return;
+            }
+        };
+
+        for item in items.iter_mut() {
+            let mut resolved = Vec::with_capacity(item.labels.len());
+            for label in item.labels.drain(..) {
+                match known.iter().find(|k| k.eq_ignore_ascii_case(&label)) {
+                    Some(existing) => resolved.push(existing.clone()),
+                    None if policy == LabelPolicy::AutoCreate => {
+                        let created: Result<LabelResponse, _> =
+                            self.post("/labels", &Label::new(&label)).await;
+                        if let Ok(created) = created {
+                            known.push(created.name.clone());
+                            resolved.push(created.name);
+                        }
+                    }
+                    // not in the allow-list
+                    None => {}
+                }
+            }
+            item.labels = resolved;
+        }
+    }
 }
 
-async fn create_project(project: Project, token: &str) -> Option<ProjectResponse> {
-    let project_json = serde_json::to_string(&project).expect("Could not convert project to json");
-    let request = init_request("POST", PROJECTS_URL, Some(&project_json), token);
+#[async_trait(?Send)]
+impl<C: HttpClient> TaskSink for Todoist<C> {
+    async fn list_projects(&self) -> Result<Vec<SinkProject>, String> {
+        let projects: Vec<ProjectResponse> = self.get("/projects").await?;
+        Ok(projects
+            .into_iter()
+            .map(|project| SinkProject {
+                id: project.id.to_string(),
+                name: project.name,
+            })
+            .collect())
+    }
 
-    let json = fetch(request).await;
-    match json {
-        Ok(json) => json.into_serde::<ProjectResponse>().ok(),
-        Err(_) => None,
+    async fn ensure_project(&self, name: &str) -> Result<String, String> {
+        let projects = self.list_projects().await?;
+        if let Some(project) = projects.iter().find(|p| p.name.eq_ignore_ascii_case(name)) {
+            return Ok(project.id.clone());
+        }
+        let created: ProjectResponse = self.post("/projects", &Project::new(name)).await?;
+        Ok(created.id.to_string())
+    }
+
+    async fn create_task(&self, project_id: &str, draft: &TaskDraft) -> Result<String, String> {
+        let project_id: u64 = project_id.parse().map_err(|_| "invalid project id")?;
+        let section_id = draft.item.section.as_ref().and_then(|name| {
+            self.section_ids
+                .borrow()
+                .iter()
+                .find(|(n, _)| n == name)
+                .map(|(_, section_id)| section_id.clone())
+        });
+        let task = Task::from_item(draft.item, project_id)
+            .with_parent_id(draft.parent_id.clone())
+            .with_section_id(section_id);
+        let created: TaskResponse = self.post("/tasks", &task).await?;
+        if draft.completed {
+            // the task exists, an open task is better than none
+            let _ = self.close_task(&created.id).await;
+        }
+        Ok(created.id)
+    }
+
+    async fn find_tasks(&self, project_id: &str) -> Result<Vec<SinkTask>, String> {
+        let tasks: Vec<OpenTask> = self
+            .get(&format!("/tasks?project_id={}", project_id))
+            .await?;
+        Ok(tasks
+            .into_iter()
+            .map(|task| SinkTask {
+                id: task.id,
+                content: task.content,
+            })
+            .collect())
+    }
+
+    async fn prepare(&self, project_id: &str, items: &mut [ListItem], options: &Options) {
+        if options.labels != LabelPolicy::AsIs && items.iter().any(|i| !i.labels.is_empty()) {
+            self.resolve_labels(items, options.labels).await;
+        }
+        // sections only for items that are uploaded
+        let uploaded: Vec<&ListItem> = items
+            .iter()
+            .filter(|item| !sink::is_skipped(item, options))
+            .collect();
+        *self.section_ids.borrow_mut() = self.ensure_sections(&uploaded, project_id).await;
     }
 }
 
-async fn create_task(task: Task, token: &str) -> Option<TaskResponse> {
-    utils::console_log("WASM - creating json for task", &task);
-    let task_json = serde_json::to_string(&task).expect("Could not convert task to json");
-    utils::console_log("WASM - created json", &"");
-    let request = init_request("POST", TASKS_URL, Some(&task_json), token);
-    utils::console_log("WASM - created-request", &"");
-
-    let json = fetch(request).await;
-    utils::console_log("WASM - sent task-request to todois-api", &"");
-    match json {
-        Ok(json) => match json.into_serde::<TaskResponse>() {
-            Ok(task_response) => {
-                utils::console_log(
-                    "WASM - todoist-response to TaskResponse was successful",
-                    &"",
-                );
-                Some(task_response)
-            }
+/// Uploads the items into the project, project 0 is a new "Einkaufsliste".
+/// Returns the id of the project.
+pub(crate) async fn make_or_update_project(
+    list_id: u32,
+    items: Vec<ListItem>,
+    token: &Secret,
+    options: &Options,
+) -> JsValue {
+    let todoist = Todoist::new(token.expose());
+    let project_id = if list_id == 0 {
+        let created: Result<ProjectResponse, _> = todoist
+            .post("/projects", &Project::new(SHOPPING_LIST))
+            .await;
+        match created {
+            Ok(project) => project.id.to_string(),
             Err(e) => {
-                utils::console_log("WASM - conversion todoist-response got error:", &e);
-                None
+                utils::console_log("WASM - could not create the shopping list", &e);
+                return JsValue::NULL;
             }
-        },
-        Err(e) => {
-            utils::console_log("WASM - error", &e);
-            None
         }
+    } else {
+        list_id.to_string()
+    };
+
+    if let Err(e) = sink::upload(&todoist, &project_id, items, options).await {
+        utils::console_log("WASM - could not create the tasks", &e);
+    }
+    match project_id.parse::<u32>() {
+        Ok(id) => JsValue::from(id),
+        Err(_) => JsValue::from_str(&project_id),
     }
 }
 
-fn init_request(mode: &str, url: &str, body: Option<&str>, token: &str) -> Request {
-    let mut opts = RequestInit::new();
-    opts.method(mode);
-    if body.is_some() {
-        opts.body(Some(&JsValue::from_str(body.unwrap())));
-    }
-    let request = Request::new_with_str_and_init(url, &opts).expect("Could not create response");
-    request
-        .headers()
-        .set("Authorization", &format!("Bearer {}", token))
-        .unwrap();
-
-    if mode == "POST" {
-        request
-            .headers()
-            .set("X-Request-Id", &js_sys::Date::now().to_string())
-            .unwrap();
-        request
-            .headers()
-            .set("Content-Type", "application/json")
-            .unwrap();
-    }
-    request
+/// The contents of all open tasks in the project.
+pub(crate) async fn fetch_task_contents(
+    project_id: u32,
+    token: &Secret,
+) -> Result<Vec<String>, String> {
+    let tasks = Todoist::new(token.expose())
+        .find_tasks(&project_id.to_string())
+        .await?;
+    Ok(tasks.into_iter().map(|task| task.content).collect())
 }
 
-pub(crate) async fn fetch_all_projects(token: &str) -> Option<JsValue> {
+/// All projects of the account as they come from todoist.
+pub(crate) async fn fetch_all_projects(token: &Secret) -> Option<JsValue> {
     utils::set_panic_hook();
-    let request = init_request("GET", PROJECTS_URL, None, token);
-
-    let window = web_sys::window().unwrap();
-    let resp_value = JsFuture::from(window.fetch_with_request(&request)).await;
-    match resp_value {
-        Ok(resp_val) => {
-            let resp: Response = resp_val.dyn_into().unwrap();
-            // Vec<ProjectResponse> as Json
-            JsFuture::from(resp.json().unwrap()).await.ok()
-        }
-        Err(_) => None,
+    let projects: serde_json::Value = Todoist::new(token.expose()).get("/projects").await.ok()?;
+    utils::to_js(&projects).ok()
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod test {
+
+    use super::Todoist;
+    use crate::checkbox::{CheckboxState, CheckedHandling};
+    use crate::http::{HttpResponse, MockServer, NativeClient};
+    use crate::options::{LabelPolicy, Options};
+    use crate::parser::ListItem;
+    use crate::sink::{upload, TaskSink};
+
+    fn task_response(id: &str, content: &str) -> HttpResponse {
+        let json = format!(
+            r#"{{"id": "{}", "project_id": "42", "order": 1, "content": "{}", "description": "",
+                "is_completed": false, "labels": [], "priority": 1, "comment_count": 0,
+                "created_at": "2023-03-11T14:02:10Z", "url": "https://todoist.com/showTask?id={}"}}"#,
+            id, content, id
+        );
+        HttpResponse::with_json(200, &json)
+    }
+
+    #[test]
+    fn tasks_get_sections_parents_and_are_closed() {
+        let server = MockServer::start(vec![
+            HttpResponse::with_json(200, r#"[{"id": "7", "project_id": "42", "name": "Obst"}]"#),
+            task_response("100", "Äpfel"),
+            task_response("101", "Birnen"),
+            HttpResponse {
+                status: 204,
+                ..HttpResponse::default()
+            },
+        ]);
+        let mut todoist = Todoist::with_client("todoist-token", NativeClient::default());
+        todoist.base_url = server.url.clone();
+        let mut options = Options::default();
+        options.checkbox.checked = CheckedHandling::Complete;
+        let items = vec![
+            ListItem {
+                content: "Äpfel".to_string(),
+                section: Some("obst".to_string()),
+                ..ListItem::default()
+            },
+            ListItem {
+                content: "Birnen".to_string(),
+                parent: Some(0),
+                checkbox: Some(CheckboxState::Checked),
+                ..ListItem::default()
+            },
+        ];
+
+        let ids = futures::executor::block_on(upload(&todoist, "42", items, &options))
+            .expect("test fails: mock server answers");
+        let requests = server.requests();
+
+        assert_eq!(vec![Some("100".to_string()), Some("101".to_string())], ids);
+        assert_eq!("/sections?project_id=42", requests[0].url);
+        let first: serde_json::Value = serde_json::from_slice(&requests[1].body).unwrap();
+        assert_eq!("7", first["section_id"]);
+        let second: serde_json::Value = serde_json::from_slice(&requests[2].body).unwrap();
+        assert_eq!("100", second["parent_id"]);
+        assert_eq!("/tasks/101/close", requests[3].url);
+    }
+
+    #[test]
+    fn tasks_with_a_date_only_due_are_created() {
+        let server = MockServer::start(vec![HttpResponse::with_json(
+            200,
+            r#"{"id": "102", "project_id": "42", "order": 1, "content": "Brot", "description": "",
+                "is_completed": false, "labels": [], "priority": 1, "comment_count": 0,
+                "created_at": "2023-03-11T14:02:10Z", "url": "https://todoist.com/showTask?id=102",
+                "due": {"string": "12 Mär", "date": "2023-03-12", "is_recurring": false}}"#,
+        )]);
+        let mut todoist = Todoist::with_client("todoist-token", NativeClient::default());
+        todoist.base_url = server.url.clone();
+        let items = vec![ListItem {
+            content: "Brot".to_string(),
+            due: Some("2023-03-12".to_string()),
+            ..ListItem::default()
+        }];
+
+        let ids = futures::executor::block_on(upload(&todoist, "42", items, &Options::default()))
+            .expect("test fails: mock server answers");
+        let requests = server.requests();
+
+        assert_eq!(vec![Some("102".to_string())], ids);
+        let task: serde_json::Value = serde_json::from_slice(&requests[0].body).unwrap();
+        assert_eq!("2023-03-12", task["due_date"]);
+    }
+
+    #[test]
+    fn open_tasks_only_need_id_and_content() {
+        let server = MockServer::start(vec![HttpResponse::with_json(
+            200,
+            r#"[{"id": "100", "content": "Milch", "due": {"date": "2023-03-12"}},
+                {"id": "101", "content": "Brot"}]"#,
+        )]);
+        let mut todoist = Todoist::with_client("todoist-token", NativeClient::default());
+        todoist.base_url = server.url.clone();
+
+        let tasks = futures::executor::block_on(todoist.find_tasks("42"))
+            .expect("test fails: tasks must parse");
+        let requests = server.requests();
+
+        let contents: Vec<_> = tasks.iter().map(|t| t.content.as_str()).collect();
+        assert_eq!(vec!["Milch", "Brot"], contents);
+        assert_eq!("/tasks?project_id=42", requests[0].url);
+    }
+
+    fn upload_with_unreadable_labels(policy: LabelPolicy) -> serde_json::Value {
+        let server = MockServer::start(vec![
+            HttpResponse::with_json(500, r#"{"error": "Internal Server Error"}"#),
+            task_response("100", "Milch"),
+        ]);
+        let mut todoist = Todoist::with_client("todoist-token", NativeClient::default());
+        todoist.base_url = server.url.clone();
+        let mut options = Options::default();
+        options.labels = policy;
+        let items = vec![ListItem {
+            content: "Milch".to_string(),
+            labels: vec!["edeka".to_string()],
+            ..ListItem::default()
+        }];
+
+        futures::executor::block_on(upload(&todoist, "42", items, &options))
+            .expect("test fails: mock server answers");
+        let requests = server.requests();
+
+        assert_eq!("/labels", requests[0].url);
+        assert_eq!("/tasks", requests[1].url);
+        serde_json::from_slice(&requests[1].body).unwrap()
+    }
+
+    #[test]
+    fn labels_pass_through_auto_create_when_the_account_labels_cannot_be_read() {
+        let task = upload_with_unreadable_labels(LabelPolicy::AutoCreate);
+
+        assert_eq!(serde_json::json!(["edeka"]), task["labels"]);
+    }
+
+    #[test]
+    fn allow_list_drops_labels_when_the_account_labels_cannot_be_read() {
+        let task = upload_with_unreadable_labels(LabelPolicy::AllowList);
+
+        assert!(task.get("labels").is_none());
+    }
+
+    #[test]
+    fn skipped_items_get_no_section() {
+        let server = MockServer::start(vec![task_response("100", "Brot")]);
+        let mut todoist = Todoist::with_client("todoist-token", NativeClient::default());
+        todoist.base_url = server.url.clone();
+        let items = vec![
+            ListItem {
+                content: "Milch".to_string(),
+                section: Some("Erledigt".to_string()),
+                checkbox: Some(CheckboxState::Checked),
+                ..ListItem::default()
+            },
+            ListItem {
+                content: "Brot".to_string(),
+                ..ListItem::default()
+            },
+        ];
+
+        futures::executor::block_on(upload(&todoist, "42", items, &Options::default()))
+            .expect("test fails: mock server answers");
+        let requests = server.requests();
+
+        assert_eq!(1, requests.len());
+        assert_eq!("/tasks", requests[0].url);
     }
 }
@@ -0,0 +1,205 @@
+use crate::http::{DefaultClient, HttpClient, HttpRequest};
+use crate::types::todoist::{CodeExchange, OAuthError, OAuthToken, RevokeToken};
+use serde::Serialize;
+
+const AUTHORIZE_URL: &str = "https://todoist.com/oauth/authorize";
+const TOKEN_URL: &str = "https://todoist.com/oauth/access_token";
+const REVOKE_URL: &str = "https://api.todoist.com/sync/v9/access_tokens/revoke";
+pub const DEFAULT_SCOPE: &str = "data:read_write";
+
+/// Where the user is sent to grant access, and the state that has to
+/// come back with the code.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Authorization {
+    pub url: String,
+    pub state: String,
+}
+
+/// The authorization-code flow of todoist, so that users log in
+/// instead of pasting their personal api token.
+/// The client secret should stay on a backend, the exchange and the
+/// revocation are here for apps that have one running this crate natively.
+pub struct TodoistOAuth<C> {
+    client_id: String,
+    client_secret: String,
+    client: C,
+    pub authorize_url: String,
+    pub token_url: String,
+    pub revoke_url: String,
+}
+
+impl TodoistOAuth<DefaultClient> {
+    pub fn new(client_id: &str, client_secret: &str) -> Self {
+        TodoistOAuth::with_client(client_id, client_secret, DefaultClient::default())
+    }
+}
+
+impl<C: HttpClient> TodoistOAuth<C> {
+    pub fn with_client(client_id: &str, client_secret: &str, client: C) -> Self {
+        TodoistOAuth {
+            client_id: client_id.to_string(),
+            client_secret: client_secret.to_string(),
+            client,
+            authorize_url: AUTHORIZE_URL.to_string(),
+            token_url: TOKEN_URL.to_string(),
+            revoke_url: REVOKE_URL.to_string(),
+        }
+    }
+
+    /// A fresh random state guards the redirect against csrf.
+    pub fn authorize(&self, scope: &str) -> Result<Authorization, String> {
+        self.authorize_with_state(scope, &uuid::Uuid::new_v4().to_string())
+    }
+
+    pub fn authorize_with_state(&self, scope: &str, state: &str) -> Result<Authorization, String> {
+        let query = serde_urlencoded::to_string([
+            ("client_id", self.client_id.as_str()),
+            ("scope", scope),
+            ("state", state),
+        ])
+        .map_err(|e| e.to_string())?;
+        Ok(Authorization {
+            url: format!("{}?{}", self.authorize_url, query),
+            state: state.to_string(),
+        })
+    }
+
+    /// Trades the code of the redirect for an access token, after checking
+    /// that the redirect carries the state of `authorize`.
+    pub async fn exchange(
+        &self,
+        code: &str,
+        returned_state: &str,
+        expected_state: &str,
+    ) -> Result<OAuthToken, String> {
+        if returned_state.is_empty() || returned_state != expected_state {
+            return Err("the state of the redirect does not match".to_string());
+        }
+        let form = serde_urlencoded::to_string(CodeExchange {
+            client_id: &self.client_id,
+            client_secret: &self.client_secret,
+            code,
+        })
+        .map_err(|e| e.to_string())?;
+        let request = HttpRequest::new("POST", &self.token_url)
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .body(form.into_bytes());
+        let response = self.client.send(request).await?;
+        if let Ok(error) = response.json::<OAuthError>() {
+            return Err(error.error);
+        }
+        response.into_json()
+    }
+
+    pub async fn revoke(&self, access_token: &str) -> Result<(), String> {
+        let body = serde_json::to_vec(&RevokeToken {
+            client_id: &self.client_id,
+            client_secret: &self.client_secret,
+            access_token,
+        })
+        .map_err(|e| e.to_string())?;
+        let request = HttpRequest::new("POST", &self.revoke_url)
+            .header("Content-Type", "application/json")
+            .body(body);
+        let response = self.client.send(request).await?;
+        if response.is_success() {
+            Ok(())
+        } else {
+            Err(format!("revoking failed with status {}", response.status))
+        }
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod test {
+
+    use super::{TodoistOAuth, DEFAULT_SCOPE};
+    use crate::http::{HttpResponse, MockServer, NativeClient, Replay};
+    use futures::executor::block_on;
+
+    fn oauth(server: &MockServer) -> TodoistOAuth<NativeClient> {
+        let mut oauth = TodoistOAuth::with_client("my-client", "secret", NativeClient::default());
+        oauth.token_url = format!("{}/oauth/access_token", server.url);
+        oauth.revoke_url = format!("{}/sync/v9/access_tokens/revoke", server.url);
+        oauth
+    }
+
+    #[test]
+    fn authorize_url_carries_client_scope_and_state() {
+        let oauth = TodoistOAuth::with_client("my-client", "secret", Replay::new(vec![]));
+
+        let authorization = oauth
+            .authorize_with_state(DEFAULT_SCOPE, "a b")
+            .expect("test fails: query must encode");
+
+        assert_eq!(
+            "https://todoist.com/oauth/authorize?client_id=my-client&scope=data%3Aread_write&state=a+b",
+            authorization.url
+        );
+        assert_ne!(
+            oauth.authorize(DEFAULT_SCOPE).unwrap().state,
+            oauth.authorize(DEFAULT_SCOPE).unwrap().state
+        );
+    }
+
+    #[test]
+    fn a_foreign_state_is_rejected_before_any_request() {
+        let oauth = TodoistOAuth::with_client("my-client", "secret", Replay::new(vec![]));
+
+        let result = block_on(oauth.exchange("code", "forged", "expected"));
+
+        assert!(result.is_err());
+        assert!(oauth.client.requests.borrow().is_empty());
+    }
+
+    #[test]
+    fn code_is_exchanged_for_a_token() {
+        let server = MockServer::start(vec![HttpResponse::with_json(
+            200,
+            r#"{"access_token": "0123456789abcdef", "token_type": "Bearer"}"#,
+        )]);
+        let oauth = oauth(&server);
+
+        let token = block_on(oauth.exchange("the-code", "state", "state"))
+            .expect("test fails: mock token endpoint answers");
+        let requests = server.requests();
+
+        assert_eq!("0123456789abcdef", token.access_token.expose());
+        assert_eq!("/oauth/access_token", requests[0].url);
+        assert_eq!(
+            "client_id=my-client&client_secret=secret&code=the-code",
+            String::from_utf8_lossy(&requests[0].body)
+        );
+    }
+
+    #[test]
+    fn token_errors_are_reported() {
+        let server = MockServer::start(vec![HttpResponse::with_json(
+            400,
+            r#"{"error": "bad_authorization_code"}"#,
+        )]);
+        let oauth = oauth(&server);
+
+        let error = block_on(oauth.exchange("old-code", "state", "state"))
+            .expect_err("test fails: the endpoint refused the code");
+        server.requests();
+
+        assert_eq!("bad_authorization_code", error);
+    }
+
+    #[test]
+    fn tokens_are_revoked() {
+        let server = MockServer::start(vec![HttpResponse {
+            status: 204,
+            ..HttpResponse::default()
+        }]);
+        let oauth = oauth(&server);
+
+        block_on(oauth.revoke("0123456789abcdef")).expect("test fails: mock answers 204");
+        let requests = server.requests();
+
+        let body: serde_json::Value = serde_json::from_slice(&requests[0].body).unwrap();
+        assert_eq!("0123456789abcdef", body["access_token"]);
+        assert_eq!("/sync/v9/access_tokens/revoke", requests[0].url);
+    }
+}
@@ -0,0 +1,472 @@
+use crate::parser::ListItem;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct TranslateConfig {
+    pub enabled: bool,
+    // language code of the canonical language, e.g. "de" or "en"
+    pub language: String,
+    // each entry is one concept, language code -> word,
+    // e.g. {"en": "oat milk", "de": "Hafermilch"}; they win over the bundled ones
+    pub dictionary: Vec<BTreeMap<String, String>>,
+}
+
+impl Default for TranslateConfig {
+    fn default() -> Self {
+        TranslateConfig {
+            enabled: false,
+            language: "de".to_string(),
+            dictionary: Vec::new(),
+        }
+    }
+}
+
+// One row per concept. The first word of a language is the canonical one,
+// further words of the same language are variants (e.g. plurals).
+const BUNDLED: &[&[(&str, &str)]] = &[
+    &[
+        ("en", "milk"),
+        ("de", "Milch"),
+        ("pt", "leite"),
+        ("es", "leche"),
+        ("fr", "lait"),
+        ("it", "latte"),
+    ],
+    &[
+        ("en", "bread"),
+        ("de", "Brot"),
+        ("pt", "pão"),
+        ("es", "pan"),
+        ("fr", "pain"),
+        ("it", "pane"),
+    ],
+    &[
+        ("en", "butter"),
+        ("de", "Butter"),
+        ("pt", "manteiga"),
+        ("es", "mantequilla"),
+        ("fr", "beurre"),
+        ("it", "burro"),
+    ],
+    &[
+        ("en", "cheese"),
+        ("de", "Käse"),
+        ("pt", "queijo"),
+        ("es", "queso"),
+        ("fr", "fromage"),
+        ("it", "formaggio"),
+    ],
+    &[
+        ("en", "eggs"),
+        ("en", "egg"),
+        ("de", "Eier"),
+        ("de", "Ei"),
+        ("pt", "ovos"),
+        ("pt", "ovo"),
+        ("es", "huevos"),
+        ("es", "huevo"),
+        ("fr", "œufs"),
+        ("fr", "oeufs"),
+        ("it", "uova"),
+    ],
+    &[
+        ("en", "water"),
+        ("de", "Wasser"),
+        ("pt", "água"),
+        ("es", "agua"),
+        ("fr", "eau"),
+        ("it", "acqua"),
+    ],
+    &[
+        ("en", "coffee"),
+        ("de", "Kaffee"),
+        ("pt", "café"),
+        ("es", "café"),
+        ("fr", "café"),
+        ("it", "caffè"),
+    ],
+    &[
+        ("en", "tea"),
+        ("de", "Tee"),
+        ("pt", "chá"),
+        ("es", "té"),
+        ("fr", "thé"),
+        ("it", "tè"),
+    ],
+    &[
+        ("en", "sugar"),
+        ("de", "Zucker"),
+        ("pt", "açúcar"),
+        ("es", "azúcar"),
+        ("fr", "sucre"),
+        ("it", "zucchero"),
+    ],
+    &[
+        ("en", "salt"),
+        ("de", "Salz"),
+        ("pt", "sal"),
+        ("es", "sal"),
+        ("fr", "sel"),
+        ("it", "sale"),
+    ],
+    &[
+        ("en", "flour"),
+        ("de", "Mehl"),
+        ("pt", "farinha"),
+        ("es", "harina"),
+        ("fr", "farine"),
+        ("it", "farina"),
+    ],
+    &[
+        ("en", "rice"),
+        ("de", "Reis"),
+        ("pt", "arroz"),
+        ("es", "arroz"),
+        ("fr", "riz"),
+        ("it", "riso"),
+    ],
+    &[
+        ("en", "pasta"),
+        ("de", "Nudeln"),
+        ("pt", "massa"),
+        ("es", "pasta"),
+        ("fr", "pâtes"),
+        ("it", "pasta"),
+    ],
+    &[
+        ("en", "olive oil"),
+        ("de", "Olivenöl"),
+        ("pt", "azeite"),
+        ("es", "aceite de oliva"),
+        ("fr", "huile d'olive"),
+        ("it", "olio d'oliva"),
+    ],
+    &[
+        ("en", "oil"),
+        ("de", "Öl"),
+        ("pt", "óleo"),
+        ("es", "aceite"),
+        ("fr", "huile"),
+        ("it", "olio"),
+    ],
+    &[
+        ("en", "apples"),
+        ("en", "apple"),
+        ("de", "Äpfel"),
+        ("de", "Apfel"),
+        ("pt", "maçãs"),
+        ("pt", "maçã"),
+        ("es", "manzanas"),
+        ("es", "manzana"),
+        ("fr", "pommes"),
+        ("fr", "pomme"),
+        ("it", "mele"),
+        ("it", "mela"),
+    ],
+    &[
+        ("en", "bananas"),
+        ("en", "banana"),
+        ("de", "Bananen"),
+        ("de", "Banane"),
+        ("pt", "bananas"),
+        ("es", "plátanos"),
+        ("fr", "bananes"),
+        ("it", "banane"),
+    ],
+    &[
+        ("en", "tomatoes"),
+        ("en", "tomato"),
+        ("de", "Tomaten"),
+        ("de", "Tomate"),
+        ("pt", "tomates"),
+        ("pt", "tomate"),
+        ("es", "tomates"),
+        ("fr", "tomates"),
+        ("it", "pomodori"),
+    ],
+    &[
+        ("en", "potatoes"),
+        ("en", "potato"),
+        ("de", "Kartoffeln"),
+        ("de", "Kartoffel"),
+        ("pt", "batatas"),
+        ("pt", "batata"),
+        ("es", "patatas"),
+        ("fr", "pommes de terre"),
+        ("it", "patate"),
+    ],
+    &[
+        ("en", "onions"),
+        ("en", "onion"),
+        ("de", "Zwiebeln"),
+        ("de", "Zwiebel"),
+        ("pt", "cebolas"),
+        ("pt", "cebola"),
+        ("es", "cebollas"),
+        ("fr", "oignons"),
+        ("it", "cipolle"),
+    ],
+    &[
+        ("en", "garlic"),
+        ("de", "Knoblauch"),
+        ("pt", "alho"),
+        ("es", "ajo"),
+        ("fr", "ail"),
+        ("it", "aglio"),
+    ],
+    &[
+        ("en", "carrots"),
+        ("en", "carrot"),
+        ("de", "Karotten"),
+        ("de", "Karotte"),
+        ("pt", "cenouras"),
+        ("pt", "cenoura"),
+        ("es", "zanahorias"),
+        ("fr", "carottes"),
+        ("it", "carote"),
+    ],
+    &[
+        ("en", "chicken"),
+        ("de", "Hähnchen"),
+        ("pt", "frango"),
+        ("es", "pollo"),
+        ("fr", "poulet"),
+        ("it", "pollo"),
+    ],
+    &[
+        ("en", "meat"),
+        ("de", "Fleisch"),
+        ("pt", "carne"),
+        ("es", "carne"),
+        ("fr", "viande"),
+        ("it", "carne"),
+    ],
+    &[
+        ("en", "fish"),
+        ("de", "Fisch"),
+        ("pt", "peixe"),
+        ("es", "pescado"),
+        ("fr", "poisson"),
+        ("it", "pesce"),
+    ],
+    &[
+        ("en", "beer"),
+        ("de", "Bier"),
+        ("pt", "cerveja"),
+        ("es", "cerveza"),
+        ("fr", "bière"),
+        ("it", "birra"),
+    ],
+    &[
+        ("en", "wine"),
+        ("de", "Wein"),
+        ("pt", "vinho"),
+        ("es", "vino"),
+        ("fr", "vin"),
+        ("it", "vino"),
+    ],
+    &[
+        ("en", "juice"),
+        ("de", "Saft"),
+        ("pt", "suco"),
+        ("pt", "sumo"),
+        ("es", "zumo"),
+        ("fr", "jus"),
+        ("it", "succo"),
+    ],
+    &[
+        ("en", "yogurt"),
+        ("en", "yoghurt"),
+        ("de", "Joghurt"),
+        ("pt", "iogurte"),
+        ("es", "yogur"),
+        ("fr", "yaourt"),
+        ("it", "yogurt"),
+    ],
+    &[
+        ("en", "toilet paper"),
+        ("de", "Klopapier"),
+        ("de", "Toilettenpapier"),
+        ("pt", "papel higiénico"),
+        ("pt", "papel higiênico"),
+        ("es", "papel higiénico"),
+        ("fr", "papier toilette"),
+        ("it", "carta igienica"),
+    ],
+    &[
+        ("en", "soap"),
+        ("de", "Seife"),
+        ("pt", "sabão"),
+        ("pt", "sabonete"),
+        ("es", "jabón"),
+        ("fr", "savon"),
+        ("it", "sapone"),
+    ],
+    &[
+        ("en", "toothpaste"),
+        ("de", "Zahnpasta"),
+        ("pt", "pasta de dentes"),
+        ("es", "pasta de dientes"),
+        ("fr", "dentifrice"),
+        ("it", "dentifricio"),
+    ],
+];
+
+/// Maps words of any known language onto the canonical language.
+pub struct Translator {
+    // (spelling in lower case, canonical word), longest phrases first
+    phrases: Vec<(String, String)>,
+}
+
+impl Translator {
+    pub fn new(config: &TranslateConfig) -> Self {
+        let user = config.dictionary.iter().map(|entry| {
+            entry
+                .iter()
+                .map(|(lang, word)| (lang.as_str(), word.as_str()))
+                .collect::<Vec<_>>()
+        });
+        let bundled = BUNDLED.iter().map(|row| row.to_vec());
+
+        let mut phrases: Vec<(String, String)> = Vec::new();
+        for row in user.chain(bundled) {
+            let Some(&(_, canonical)) = row.iter().find(|(lang, _)| *lang == config.language)
+            else {
+                continue;
+            };
+            for &(_, word) in &row {
+                let spelling = word.to_lowercase();
+                // earlier rows (the user's) win
+                if !phrases.iter().any(|(s, _)| *s == spelling) {
+                    phrases.push((spelling, canonical.to_string()));
+                }
+            }
+        }
+        phrases.sort_by_key(|(spelling, _)| std::cmp::Reverse(spelling.split(' ').count()));
+        Translator { phrases }
+    }
+
+    /// Returns the translated text, or `None` if nothing was translated.
+    pub fn translate(&self, text: &str) -> Option<String> {
+        let words: Vec<&str> = text.split_whitespace().collect();
+        let lower: Vec<String> = words.iter().map(|w| w.to_lowercase()).collect();
+
+        let mut translated = Vec::with_capacity(words.len());
+        let mut changed = false;
+        let mut i = 0;
+        while i < words.len() {
+            let found = self.phrases.iter().find(|(spelling, _)| {
+                let len = spelling.split(' ').count();
+                i + len <= words.len() && lower[i..i + len].join(" ") == *spelling
+            });
+            match found {
+                Some((spelling, canonical)) => {
+                    let len = spelling.split(' ').count();
+                    changed |= words[i..i + len].join(" ") != *canonical;
+                    translated.push(canonical.clone());
+                    i += len;
+                }
+                None => {
+                    translated.push(words[i].to_string());
+                    i += 1;
+                }
+            }
+        }
+        changed.then(|| translated.join(" "))
+    }
+}
+
+/// Translates the content of every item and keeps the text as it was
+/// written in the description.
+pub fn apply(items: &mut [ListItem], config: &TranslateConfig) {
+    if !config.enabled {
+        return;
+    }
+    let translator = Translator::new(config);
+    for item in items.iter_mut() {
+        let Some(translated) = translator.translate(&item.content) else {
+            continue;
+        };
+        let written = std::mem::replace(&mut item.content, translated);
+        item.description = Some(match item.description.take() {
+            Some(description) => format!("{}\n{}", description, written),
+            None => written.clone(),
+        });
+        item.original.get_or_insert(written);
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    extern crate wasm_bindgen_test;
+
+    use wasm_bindgen_test::*;
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    use super::{apply, TranslateConfig, Translator};
+    use crate::parser::ListItem;
+    use std::collections::BTreeMap;
+
+    fn config(language: &str) -> TranslateConfig {
+        TranslateConfig {
+            enabled: true,
+            language: language.to_string(),
+            ..TranslateConfig::default()
+        }
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn mixed_languages_become_canonical() {
+        let translator = Translator::new(&config("de"));
+        assert_eq!(Some("Milch".to_string()), translator.translate("Milk"));
+        assert_eq!(Some("Brot".to_string()), translator.translate("pão"));
+        assert_eq!(Some("2x Eier".to_string()), translator.translate("2x eggs"));
+        assert_eq!(None, translator.translate("Brot"));
+        assert_eq!(None, translator.translate("Geschenk"));
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn phrases_win_over_single_words() {
+        let translator = Translator::new(&config("de"));
+        assert_eq!(
+            Some("Olivenöl".to_string()),
+            translator.translate("olive oil")
+        );
+        let translator = Translator::new(&config("en"));
+        assert_eq!(
+            Some("toilet paper".to_string()),
+            translator.translate("Klopapier")
+        );
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn user_dictionary_extends_and_overrides() {
+        let mut entry = BTreeMap::new();
+        entry.insert("en".to_string(), "oat milk".to_string());
+        entry.insert("de".to_string(), "Hafermilch".to_string());
+        let config = TranslateConfig {
+            dictionary: vec![entry],
+            ..config("de")
+        };
+        let translator = Translator::new(&config);
+        assert_eq!(
+            Some("Hafermilch".to_string()),
+            translator.translate("oat milk")
+        );
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn written_text_goes_to_the_description() {
+        let mut items = vec![ListItem {
+            content: "leite".to_string(),
+            ..ListItem::default()
+        }];
+        apply(&mut items, &config("de"));
+        assert_eq!("Milch", items[0].content);
+        assert_eq!(Some("leite".to_string()), items[0].description);
+        assert_eq!(Some("leite".to_string()), items[0].original);
+    }
+}
@@ -119,6 +119,8 @@ pub mod vision_api {
 }
 
 pub mod todoist {
+    use crate::parser::ListItem;
+    use crate::secret::Secret;
     use serde::{Deserialize, Serialize};
 
     // ###### todoist types #######
@@ -150,12 +152,23 @@ pub mod todoist {
     #[derive(Debug, Deserialize, Serialize, Default)]
     pub struct Task {
         content: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        description: Option<String>,
         project_id: Option<u64>,
         label_ids: Vec<u32>,
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        labels: Vec<String>,
         // only one due_* can be used
         due_string: Option<String>,
         due_date: Option<String>,
         due_datetime: Option<String>,
+        // 1 (normal) to 4 (urgent)
+        #[serde(skip_serializing_if = "Option::is_none")]
+        priority: Option<u8>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        parent_id: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        section_id: Option<String>,
     }
     impl Task {
         pub fn new(content: &str, project_id: u64) -> Self {
@@ -164,18 +177,81 @@ pub mod todoist {
             task.project_id = Some(project_id);
             task
         }
+
+        pub fn from_item(item: &ListItem, project_id: u64) -> Self {
+            Task {
+                priority: item.priority,
+                labels: item.labels.clone(),
+                description: item.description.clone(),
+                due_date: item.due.clone(),
+                ..Task::new(&item.content, project_id)
+            }
+        }
+
+        pub fn with_parent_id(mut self, parent_id: Option<String>) -> Self {
+            self.parent_id = parent_id;
+            self
+        }
+
+        pub fn with_section_id(mut self, section_id: Option<String>) -> Self {
+            self.section_id = section_id;
+            self
+        }
+    }
+
+    #[derive(Debug, Serialize)]
+    pub struct Label {
+        name: String,
+    }
+    impl Label {
+        pub fn new(name: &str) -> Self {
+            Label {
+                name: name.to_string(),
+            }
+        }
+    }
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    pub struct LabelResponse {
+        pub id: String,
+        pub name: String,
+        pub color: Option<String>,
+        pub order: Option<u32>,
+        pub is_favorite: Option<bool>,
+    }
+
+    #[derive(Debug, Serialize)]
+    pub struct Section {
+        name: String,
+        project_id: String,
+    }
+    impl Section {
+        pub fn new(name: &str, project_id: &str) -> Self {
+            Section {
+                name: name.to_string(),
+                project_id: project_id.to_string(),
+            }
+        }
+    }
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    pub struct SectionResponse {
+        pub id: String,
+        pub project_id: String,
+        pub order: Option<u32>,
+        pub name: String,
     }
 
     #[derive(Debug, Deserialize, Default)]
     pub(crate) struct TaskResponse {
-        id: String,
+        pub(crate) id: String,
         assigner_id: Option<String>,
         assignee_id: Option<String>,
         project_id: String,
         section_id: Option<String>,
         parent_id: Option<String>,
         order: u32,
-        content: String,
+        pub(crate) content: String,
         description: String,
         is_completed: bool,
         labels: Vec<String>,
@@ -187,12 +263,300 @@ pub mod todoist {
         url: String,
     }
 
+    /// What the history of a project needs of its open tasks.
+    #[derive(Debug, Deserialize)]
+    pub(crate) struct OpenTask {
+        pub(crate) id: String,
+        pub(crate) content: String,
+    }
+
+    // ###### oauth #######
+    /// Form of the code exchange at the token endpoint.
+    #[derive(Serialize)]
+    pub struct CodeExchange<'a> {
+        pub client_id: &'a str,
+        pub client_secret: &'a str,
+        pub code: &'a str,
+    }
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+    pub struct OAuthToken {
+        pub access_token: Secret,
+        pub token_type: String,
+    }
+
+    /// What todoist answers a failed exchange with.
+    #[derive(Debug, Deserialize)]
+    pub struct OAuthError {
+        pub error: String,
+    }
+
+    #[derive(Serialize)]
+    pub struct RevokeToken<'a> {
+        pub client_id: &'a str,
+        pub client_secret: &'a str,
+        pub access_token: &'a str,
+    }
+
     #[derive(Debug, Deserialize, Default)]
     pub struct Due {
         string: String,
         date: String,
         is_recurring: bool,
-        datetime: String,
-        timezone: String,
+        // only set for dues with a time
+        datetime: Option<String>,
+        timezone: Option<String>,
+    }
+}
+
+pub mod azure_read {
+    use serde::Deserialize;
+
+    #[derive(Deserialize, Debug)]
+    #[serde(rename_all = "camelCase")]
+    pub struct ReadOperation {
+        // notStarted, running, succeeded or failed
+        pub status: String,
+        pub analyze_result: Option<AnalyzeResult>,
+    }
+
+    #[derive(Deserialize, Debug)]
+    #[serde(rename_all = "camelCase")]
+    pub struct AnalyzeResult {
+        // one per page
+        pub read_results: Vec<ReadResult>,
+    }
+
+    #[derive(Deserialize, Debug)]
+    pub struct ReadResult {
+        pub page: u32,
+        pub lines: Vec<Line>,
+    }
+
+    #[derive(Deserialize, Debug)]
+    #[serde(rename_all = "camelCase")]
+    pub struct Line {
+        pub text: String,
+        // x1, y1, ..., x4, y4 clockwise from the top left corner
+        pub bounding_box: Vec<f64>,
+        pub words: Vec<Word>,
+    }
+
+    #[derive(Deserialize, Debug)]
+    #[serde(rename_all = "camelCase")]
+    pub struct Word {
+        pub text: String,
+        pub bounding_box: Vec<f64>,
+        pub confidence: f32,
+    }
+
+    #[derive(Deserialize, Debug)]
+    pub struct ErrorResponse {
+        pub error: ApiError,
+    }
+
+    #[derive(Deserialize, Debug)]
+    pub struct ApiError {
+        pub code: String,
+        pub message: String,
+    }
+}
+
+pub mod textract {
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Debug)]
+    #[serde(rename_all = "PascalCase")]
+    pub struct DetectDocumentText {
+        pub document: Document,
+    }
+
+    #[derive(Serialize, Debug)]
+    #[serde(rename_all = "PascalCase")]
+    pub struct Document {
+        // base64 encoded image
+        pub bytes: String,
+    }
+
+    #[derive(Deserialize, Debug)]
+    #[serde(rename_all = "PascalCase")]
+    pub struct DetectDocumentTextResponse {
+        pub blocks: Vec<Block>,
+    }
+
+    #[derive(Deserialize, Debug)]
+    #[serde(rename_all = "PascalCase")]
+    pub struct Block {
+        // PAGE, LINE or WORD
+        pub block_type: String,
+        pub id: String,
+        pub text: Option<String>,
+        // 0 to 100
+        pub confidence: Option<f32>,
+        pub geometry: Option<Geometry>,
+        #[serde(default)]
+        pub relationships: Vec<Relationship>,
+    }
+
+    #[derive(Deserialize, Debug)]
+    #[serde(rename_all = "PascalCase")]
+    pub struct Geometry {
+        pub bounding_box: BoundingBox,
+    }
+
+    /// Relative to the size of the page, 0.0 to 1.0.
+    #[derive(Deserialize, Debug)]
+    #[serde(rename_all = "PascalCase")]
+    pub struct BoundingBox {
+        pub width: f64,
+        pub height: f64,
+        pub left: f64,
+        pub top: f64,
+    }
+
+    #[derive(Deserialize, Debug)]
+    #[serde(rename_all = "PascalCase")]
+    pub struct Relationship {
+        // CHILD points from a line to its words
+        pub r#type: String,
+        pub ids: Vec<String>,
+    }
+
+    #[derive(Deserialize, Debug)]
+    pub struct ApiError {
+        #[serde(rename = "__type")]
+        pub kind: String,
+        #[serde(alias = "Message")]
+        pub message: String,
+    }
+}
+
+pub mod ms_todo {
+    use serde::{Deserialize, Serialize};
+
+    /// Every list-endpoint of the graph api answers with one page of these.
+    #[derive(Deserialize, Debug)]
+    pub struct Page<T> {
+        pub value: Vec<T>,
+        #[serde(rename = "@odata.nextLink")]
+        pub next_link: Option<String>,
+    }
+
+    #[derive(Serialize, Deserialize, Debug, Default)]
+    #[serde(rename_all = "camelCase")]
+    pub struct TodoList {
+        #[serde(skip_serializing)]
+        pub id: String,
+        pub display_name: String,
+    }
+
+    #[derive(Serialize, Deserialize, Debug, Default)]
+    #[serde(rename_all = "camelCase")]
+    pub struct TodoTask {
+        #[serde(skip_serializing)]
+        pub id: String,
+        pub title: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub body: Option<ItemBody>,
+        // low, normal or high
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub importance: Option<String>,
+        // notStarted or completed (among others)
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub status: Option<String>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        pub categories: Vec<String>,
+    }
+
+    #[derive(Serialize, Deserialize, Debug)]
+    #[serde(rename_all = "camelCase")]
+    pub struct ItemBody {
+        pub content: String,
+        pub content_type: String,
+    }
+
+    /// The steps of a task, used for subtasks.
+    #[derive(Serialize, Deserialize, Debug, Default)]
+    #[serde(rename_all = "camelCase")]
+    pub struct ChecklistItem {
+        #[serde(skip_serializing)]
+        pub id: String,
+        pub display_name: String,
+        pub is_checked: bool,
+    }
+}
+
+pub mod google_tasks {
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Deserialize, Debug)]
+    #[serde(rename_all = "camelCase")]
+    pub struct Page<T> {
+        // missing on empty lists
+        #[serde(default = "Vec::new")]
+        pub items: Vec<T>,
+        pub next_page_token: Option<String>,
+    }
+
+    #[derive(Serialize, Deserialize, Debug, Default)]
+    pub struct TaskList {
+        #[serde(skip_serializing)]
+        pub id: String,
+        pub title: String,
+    }
+
+    #[derive(Serialize, Deserialize, Debug, Default)]
+    pub struct Task {
+        #[serde(skip_serializing)]
+        pub id: String,
+        pub title: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub notes: Option<String>,
+        // needsAction or completed
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub status: Option<String>,
+    }
+}
+
+pub mod google_oauth {
+    use crate::secret::Secret;
+    use serde::{Deserialize, Serialize};
+
+    /// Form of the code exchange, `client_secret` is only
+    /// sent for clients that have one.
+    #[derive(Serialize)]
+    pub struct CodeExchange<'a> {
+        pub client_id: &'a str,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub client_secret: Option<&'a str>,
+        pub code: &'a str,
+        pub code_verifier: &'a str,
+        pub grant_type: &'a str,
+        pub redirect_uri: &'a str,
+    }
+
+    #[derive(Serialize)]
+    pub struct Refresh<'a> {
+        pub client_id: &'a str,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub client_secret: Option<&'a str>,
+        pub refresh_token: &'a str,
+        pub grant_type: &'a str,
+    }
+
+    #[derive(Deserialize, Debug)]
+    pub struct TokenResponse {
+        pub access_token: Secret,
+        pub expires_in: i64,
+        // only on the first exchange (with access_type=offline)
+        pub refresh_token: Option<Secret>,
+        #[serde(default)]
+        pub scope: String,
+    }
+
+    #[derive(Deserialize, Debug)]
+    pub struct ErrorResponse {
+        pub error: String,
+        pub error_description: Option<String>,
     }
 }
@@ -1,6 +1,4 @@
-use wasm_bindgen::{JsCast, JsValue};
-use wasm_bindgen_futures::JsFuture;
-use web_sys::{Request, Response};
+use wasm_bindgen::JsValue;
 
 pub fn set_panic_hook() {
     // When the `console_error_panic_hook` feature is enabled, we can call the
@@ -13,16 +11,18 @@ pub fn set_panic_hook() {
     console_error_panic_hook::set_once();
 }
 
-pub async fn fetch(request: Request) -> Result<JsValue, JsValue> {
-    let window = web_sys::window().unwrap();
-    let resp_value = JsFuture::from(window.fetch_with_request(&request)).await;
-    match resp_value {
-        Ok(resp_value) => {
-            let resp: Response = resp_value.dyn_into().unwrap();
-            JsFuture::from(resp.json().unwrap()).await
-        }
-        Err(e) => Err(e),
-    }
+/// Hands a rust type over to javascript as a plain object.
+pub fn to_js<T>(value: &T) -> Result<JsValue, JsValue>
+where
+    T: serde::Serialize,
+{
+    let json = serde_json::to_string(value).map_err(|e| JsValue::from_str(&e.to_string()))?;
+    js_sys::JSON::parse(&json)
+}
+
+/// The message of a javascript error, for code that reports errors as `String`.
+pub fn error_string(value: &JsValue) -> String {
+    value.as_string().unwrap_or_else(|| format!("{:?}", value))
 }
 
 pub fn console_log<JS>(ident: &str, value: &JS)
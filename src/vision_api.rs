@@ -1,167 +1,156 @@
-use crate::auth;
-use crate::jwt;
+use crate::credentials::{GoogleAuth, GoogleCredentials, TokenProvider};
+use crate::http::{DefaultClient, HttpClient, HttpRequest};
+use crate::layout::{TextLine, WordBox};
+use crate::ocr::{Document, OcrEngine};
 use crate::types::vision_api as va;
 use crate::types::vision_api::EntityAnnotation;
-use crate::utils::{console_log, fetch};
-use wasm_bindgen::prelude::*;
-use web_sys::{Request, RequestInit};
+use async_trait::async_trait;
 
 const VISION_API_URL: &str = "https://vision.googleapis.com/v1/images:annotate";
-
-pub(crate) async fn image_to_list_items(
-    img_data: String,
-    credentials_json: &str,
-) -> Result<TodoItem, JsValue> {
-    image_to_text(img_data, credentials_json, false).await
+// the audience of self-signed jwts
+const VISION_SERVICE_URL: &str = "https://vision.googleapis.com/";
+
+/// Google Vision as `OcrEngine`. Where the access token comes from is up
+/// to the `TokenProvider`, see `GoogleCredentials` for the accepted json.
+pub struct GoogleVision<C> {
+    tokens: Box<dyn TokenProvider>,
+    client: C,
+    pub url: String,
 }
 
-pub(crate) async fn image_to_single_item(
-    img_data: String,
-    credentials_json: &str,
-) -> Result<TodoItem, JsValue> {
-    image_to_text(img_data, credentials_json, true).await
-}
+impl GoogleVision<DefaultClient> {
+    pub fn new(credentials_json: &str, auth: GoogleAuth) -> Result<Self, String> {
+        let credentials = GoogleCredentials::from_json(credentials_json)?;
+        Ok(GoogleVision::with_provider(
+            credentials.into_provider(auth, VISION_SERVICE_URL),
+        ))
+    }
 
-#[derive(Debug)]
-pub(crate) enum TodoItem {
-    Single(String),
-    List(Vec<String>),
+    pub fn with_provider(tokens: Box<dyn TokenProvider>) -> Self {
+        GoogleVision::with_client(tokens, DefaultClient::default())
+    }
 }
 
-async fn image_to_text(
-    img_data: String,
-    credentials_json: &str,
-    single_todo: bool,
-) -> Result<TodoItem, JsValue> {
-    let jwt = jwt::create_jwt(credentials_json).expect("Could not create jwt");
-    let access_token = auth::get_access_token(&jwt).await?;
+impl<C: HttpClient> GoogleVision<C> {
+    pub fn with_client(tokens: Box<dyn TokenProvider>, client: C) -> Self {
+        GoogleVision {
+            tokens,
+            client,
+            url: VISION_API_URL.to_string(),
+        }
+    }
 
-    let api_res_json = ask_google_vision_api(img_data, access_token.access_token).await?;
-    console_log("WASM - vision_api.rs", &"google answered with token");
+    /// The first (and only) response to the image.
+    pub(crate) async fn annotate(&self, img_data: &str) -> Result<va::Response, String> {
+        let access_token = self.tokens.access_token().await?;
+        let body = serde_json::to_vec(&va::Requests::from(img_data.to_string()))
+            .map_err(|e| e.to_string())?;
+        let request = HttpRequest::new("POST", &self.url)
+            .bearer(&access_token)
+            .header("Content-Type", "application/json")
+            .body(body);
+        let responses: va::Responses = self.client.send(request).await?.into_json()?;
+        responses
+            .responses
+            .into_iter()
+            .next()
+            .ok_or_else(|| "ok-response must have one element".to_string())
+    }
+}
 
-    let response = api_res_json
-        .responses
-        .into_iter()
-        .next()
-        .expect("ok-response must have one element");
+#[async_trait(?Send)]
+impl<C: HttpClient> OcrEngine for GoogleVision<C> {
+    async fn recognize(&self, img_data: &str) -> Result<Document, String> {
+        let response = self.annotate(img_data).await?;
+        response_to_document(response)
+    }
+}
 
-    let text_from_api = match (
+fn response_to_document(response: va::Response) -> Result<Document, String> {
+    match (
         response.text_annotations,
         response.full_text_annotation,
         response.error,
     ) {
-        (Some(text_annotations), Some(full_text_annotation), None) => {
-            if single_todo {
-                TodoItem::Single(find_largest_item(text_annotations))
-            } else {
-                TodoItem::List(
-                    full_text_annotation
-                        .text
-                        .split_terminator('\n')
-                        .map(str::to_string)
-                        .collect(),
-                )
-            }
-        }
-        (None, None, Some(error)) => return Err(JsValue::from_str(&error.message)),
-        _ => return Err(JsValue::from_str("unexpected structure")),
-    };
-    console_log("WASM - vision_api.rs", &text_from_api);
-    Ok(text_from_api)
+        (Some(text_annotations), Some(full_text_annotation), None) => Ok(Document {
+            lines: annotations_to_lines(&full_text_annotation.text, &text_annotations),
+            text: full_text_annotation.text,
+        }),
+        (None, None, Some(error)) => Err(error.message),
+        _ => Err("unexpected structure".to_string()),
+    }
 }
 
-pub(crate) async fn ask_google_vision_api(
-    img_data: String,
-    access_token: String,
-) -> Result<va::Responses, JsValue> {
-    let requests_obj = va::Requests::from(img_data);
-    let request_obj_json = serde_json::to_string(&requests_obj).unwrap();
-    console_log(
-        "WASM - vision_api.rs",
-        &"constructed image-translate-request-object",
-    );
-
-    let request = init_request(&request_obj_json, &access_token);
-
-    let res = fetch(request).await?;
-    console_log(
-        "WASM - vision_api.rs",
-        &"Sent image and access-token to vision-API and got response",
-    );
-
-    #[cfg(test)]
-    console_log("WASM - vision_api.rs", &format!("got response: ${res:?}"));
-
-    match res.into_serde::<va::Responses>() {
-        Ok(api_data_json) => Ok(api_data_json),
-        Err(e) => Err(JsValue::from_str(&e.to_string())),
+/// Splits the full text into lines and finds the geometry of each line
+/// by walking through the single words, which google returns in reading
+/// order after the first annotation (that one spans the entire text).
+fn annotations_to_lines(full_text: &str, text_annotations: &[EntityAnnotation]) -> Vec<TextLine> {
+    // the first one is the entire text, which has no line break for a single word
+    let mut words = text_annotations.iter().skip(1);
+
+    let mut lines = Vec::new();
+    for text in full_text.split_terminator('\n') {
+        let line_len = text.chars().filter(|c| !c.is_whitespace()).count();
+        let mut consumed = 0;
+        let mut line_words = Vec::new();
+        while consumed < line_len {
+            let Some(word) = words.next() else {
+                break;
+            };
+            consumed += word.description.chars().count();
+            line_words.push(word_box(word));
+        }
+        lines.push(TextLine::from_words(text, line_words));
     }
+    lines
 }
 
-fn init_request(request_obj: &str, access_token: &str) -> Request {
-    let mut opts = RequestInit::new();
-    opts.method("POST");
-    opts.body(Some(&JsValue::from_str(request_obj)));
-    let request =
-        Request::new_with_str_and_init(VISION_API_URL, &opts).expect("Could not create response");
-    request
-        .headers()
-        .set("Authorization", &format!("Bearer {}", access_token))
-        .unwrap();
-    request
-        .headers()
-        .set("Content-Type", "application/json")
-        .unwrap();
-    request
+fn word_box(annotation: &EntityAnnotation) -> WordBox {
+    let vs = &annotation.bounding_poly.vertices;
+    let x = |v: &va::VerticeOption| v.x.unwrap_or(0);
+    let y = |v: &va::VerticeOption| v.y.unwrap_or(0);
+    let left = x(&vs.top_left).min(x(&vs.bottom_left));
+    let right = x(&vs.top_right).max(x(&vs.bottom_right));
+    let top = y(&vs.top_left).min(y(&vs.top_right));
+    let bottom = y(&vs.bottom_left).max(y(&vs.bottom_right));
+    WordBox {
+        text: annotation.description.clone(),
+        left,
+        top,
+        width: right.saturating_sub(left),
+        height: bottom.saturating_sub(top),
+        // text annotations come without a confidence
+        confidence: None,
+    }
 }
 
-fn find_largest_item(text_annotations: Vec<EntityAnnotation>) -> String {
-    text_annotations
-        .into_iter()
-        // do not consider the entire picture (spanning over multiple lines)
-        .filter(|e| !e.description.contains('\n'))
-        .map(|e| (e.description, e.bounding_poly.vertices))
-        .map(|(e, vs)| (e, (vs.bottom_left - vs.top_left)))
-        .max_by_key(|(e, sizes)| {
-            #[cfg(test)]
-            console_log("finding-largest", &format!("item: {e}, sizes: {sizes:?}"));
-            sizes.y
-        })
-        .expect("one must be the largest")
-        .0
-}
 #[cfg(test)]
 mod test {
 
     extern crate wasm_bindgen_test;
 
-    use wasm_bindgen::JsValue;
     use wasm_bindgen_test::*;
     wasm_bindgen_test_configure!(run_in_browser);
 
     use crate::{
-        auth, jwt,
+        credentials::{GoogleAuth, StaticToken},
+        secret::Secret,
         types::vision_api::{EntityAnnotation, FullTextAnnotation, Response},
-        utils,
-        vision_api::find_largest_item,
+        vision_api::{annotations_to_lines, response_to_document},
     };
 
-    use super::ask_google_vision_api;
+    use super::GoogleVision;
     use wasm_bindgen_test::wasm_bindgen_test;
     const GOOGLE_VISION_API_KEY: &str = include_str!("../vision-api-key.json");
 
     #[wasm_bindgen_test]
     async fn fails_without_token() {
-        let response = ask_google_vision_api("picture-data".to_string(), "token".to_string()).await;
-        assert!(response.is_err());
-        let Err(e) = response else {
-            unreachable!("we checked it's an error")
-        };
-        let has_correct_error = e
-            .as_string()
-            .expect("test fails: JsValue cannot be converted to string")
-            .contains("missing field `responses`");
-        assert!(has_correct_error);
+        let vision = GoogleVision::with_provider(Box::new(StaticToken(Secret::from("token"))));
+
+        let response = vision.annotate("picture-data").await;
+
+        let error = response.expect_err("test fails: the token is invalid");
+        assert!(error.contains("status 401"));
     }
 
     #[wasm_bindgen_test]
@@ -204,39 +193,104 @@ mod test {
 
         assert!(!response.is_err());
 
-        let (text_annotations, _) = extract_test_response(response);
+        let document = response_to_document(response.expect("we checked it's not an error"))
+            .expect("test fails: response must contain text");
 
-        let largest_item = find_largest_item(text_annotations);
+        let largest_item = document.largest_word().expect("one must be the largest");
         let expected_data = "Mythos";
-        assert_eq!(expected_data, largest_item);
-    }
-
-    async fn make_authenticated_test_request(mock_data: &[u8]) -> Result<Response, JsValue> {
-        let jwt = jwt::create_jwt(GOOGLE_VISION_API_KEY)
-            .expect("test fails: could not create jwt from credentials");
-        let access_token = auth::get_access_token(&jwt)
-            .await
-            .expect("test fails: could not load an access_token");
-
-        let mock_picture_data = base64::encode(mock_data);
-
-        let response = ask_google_vision_api(mock_picture_data, access_token.access_token).await;
-        utils::console_log("zero_bytes_test", &format!("${response:?}"));
-        match response {
-            Ok(res) => {
-                let res = res
-                    .responses
-                    .into_iter()
-                    .next()
-                    .expect("test fails: 0th element should be present");
-                Ok(res)
-            }
-            Err(e) => Err(e),
-        }
+        assert_eq!(expected_data, largest_item.text);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn words_are_grouped_into_lines() {
+        let annotations: Vec<EntityAnnotation> = serde_json::from_str(
+            r#"[
+                {"description": "Party:\nChips\nCola", "boundingPoly": {"vertices": [{}, {"x": 90}, {"x": 90, "y": 95}, {"y": 95}]}},
+                {"description": "Party", "boundingPoly": {"vertices": [{"x": 10, "y": 5}, {"x": 70, "y": 5}, {"x": 70, "y": 30}, {"x": 10, "y": 30}]}},
+                {"description": ":", "boundingPoly": {"vertices": [{"x": 71, "y": 5}, {"x": 75, "y": 5}, {"x": 75, "y": 30}, {"x": 71, "y": 30}]}},
+                {"description": "Chips", "boundingPoly": {"vertices": [{"x": 40, "y": 36}, {"x": 90, "y": 36}, {"x": 90, "y": 60}, {"x": 40, "y": 60}]}},
+                {"description": "Cola", "boundingPoly": {"vertices": [{"x": 42, "y": 68}, {"x": 80, "y": 68}, {"x": 80, "y": 95}, {"x": 42, "y": 95}]}}
+            ]"#,
+        )
+        .expect("test fails: annotations must deserialize");
+
+        let lines = annotations_to_lines("Party:\nChips\nCola\n", &annotations);
+
+        let texts: Vec<_> = lines.iter().map(|l| l.text.as_str()).collect();
+        assert_eq!(vec!["Party:", "Chips", "Cola"], texts);
+        let lefts: Vec<_> = lines.iter().map(|l| l.left).collect();
+        assert_eq!(vec![10, 40, 42], lefts);
+        assert_eq!(25, lines[0].height);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn a_single_word_is_not_taken_for_the_full_text() {
+        let annotations: Vec<EntityAnnotation> = serde_json::from_str(
+            r#"[
+                {"description": "Milch", "boundingPoly": {"vertices": [{}, {"x": 90}, {"x": 90, "y": 40}, {"y": 40}]}},
+                {"description": "Milch", "boundingPoly": {"vertices": [{"x": 10, "y": 5}, {"x": 70, "y": 5}, {"x": 70, "y": 30}, {"x": 10, "y": 30}]}}
+            ]"#,
+        )
+        .expect("test fails: annotations must deserialize");
+
+        let lines = annotations_to_lines("Milch\n", &annotations);
+
+        assert_eq!(1, lines.len());
+        assert_eq!(10, lines[0].left);
+        assert_eq!(25, lines[0].height);
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    fn recognizes_items_without_a_browser() {
+        use crate::http::{HttpResponse, MockServer, NativeClient};
+        use crate::options::Options;
+        use crate::pipeline::Context;
+
+        let server = MockServer::start(vec![HttpResponse::with_json(
+            200,
+            r#"{"responses": [{
+                "textAnnotations": [
+                    {"description": "Milch\nBrot", "boundingPoly": {"vertices": [{}, {"x": 60}, {"x": 60, "y": 60}, {"y": 60}]}},
+                    {"description": "Milch", "boundingPoly": {"vertices": [{"x": 10, "y": 5}, {"x": 60, "y": 5}, {"x": 60, "y": 25}, {"x": 10, "y": 25}]}},
+                    {"description": "Brot", "boundingPoly": {"vertices": [{"x": 10, "y": 35}, {"x": 50, "y": 35}, {"x": 50, "y": 60}, {"x": 10, "y": 60}]}}
+                ],
+                "fullTextAnnotation": {"text": "Milch\nBrot\n"}
+            }]}"#,
+        )]);
+        let mut vision = GoogleVision::with_client(
+            Box::new(StaticToken(Secret::from("ya29.token"))),
+            NativeClient::default(),
+        );
+        vision.url = format!("{}/v1/images:annotate", server.url);
+
+        let items = futures::executor::block_on(crate::recognize_items(
+            &vision,
+            "aW1hZ2U=",
+            &Options::default(),
+            false,
+            &Context::default(),
+        ))
+        .expect("test fails: mock server answers");
+        let requests = server.requests();
+
+        let contents: Vec<_> = items.iter().map(|i| i.content.as_str()).collect();
+        assert_eq!(vec!["Milch", "Brot"], contents);
+        assert_eq!("/v1/images:annotate", requests[0].url);
+        assert!(requests[0]
+            .headers
+            .contains(&("authorization".to_string(), "Bearer ya29.token".to_string())));
+    }
+
+    async fn make_authenticated_test_request(mock_data: &[u8]) -> Result<Response, String> {
+        let vision = GoogleVision::new(GOOGLE_VISION_API_KEY, GoogleAuth::default())
+            .expect("test fails: credentials must parse");
+
+        vision.annotate(&base64::encode(mock_data)).await
     }
 
     fn extract_test_response(
-        api_response: Result<Response, JsValue>,
+        api_response: Result<Response, String>,
     ) -> (Vec<EntityAnnotation>, FullTextAnnotation) {
         let response = api_response.expect("we checked it's not an error");
         let Response {